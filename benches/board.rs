@@ -0,0 +1,139 @@
+// Compares the bitboard Board with the Vec<Vec<Option<Piece>>> board it replaced.
+// Run with `cargo bench`
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use chess::chess::{Board, Game, Piece, Square};
+
+const POSITIONS: [(&str, &str); 2] = [
+    (
+        "start",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    ),
+    (
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    ),
+];
+
+const KNIGHT_JUMPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+// The previous board, kept here so the two can be compared
+struct VecBoard {
+    squares: Vec<Vec<Option<Piece>>>,
+}
+
+impl VecBoard {
+    fn new(board: &Board) -> VecBoard {
+        let mut squares = vec![vec![None; 8]; 8];
+        for (square, piece) in board.get_all_pieces() {
+            squares[square.file_index()][square.rank_index()] = Some(*piece);
+        }
+        VecBoard { squares }
+    }
+
+    fn get(&self, square: &Square) -> Option<&Piece> {
+        self.squares[square.file_index()][square.rank_index()].as_ref()
+    }
+
+    fn get_offset(
+        &self,
+        square: &Square,
+        file_offset: i8,
+        rank_offset: i8,
+    ) -> Option<(Square, Option<&Piece>)> {
+        let file_index = square.file_index() as i8 + file_offset;
+        let rank_index = square.rank_index() as i8 + rank_offset;
+        if file_index < 0 || rank_index < 0 || file_index >= 8 || rank_index >= 8 {
+            return None;
+        }
+        let square = Square::from_index(file_index as usize, rank_index as usize);
+        Some((square, self.get(&square)))
+    }
+
+    fn get_all_squares(&self) -> Vec<(Square, Option<&Piece>)> {
+        let mut pieces = Vec::new();
+        for file_index in 0..8 {
+            for rank_index in 0..8 {
+                let square = Square::from_index(file_index, rank_index);
+                pieces.push((square, self.get(&square)));
+            }
+        }
+        pieces
+    }
+
+    fn get_all_pieces(&self) -> Vec<(Square, &Piece)> {
+        self.get_all_squares()
+            .iter()
+            .filter(|(_, piece)| piece.is_some())
+            .map(|(square, piece)| (*square, piece.unwrap()))
+            .collect()
+    }
+}
+
+// Run a function repeatedly for a fixed time and report the average time per call
+fn bench<F: FnMut()>(name: &str, mut f: F) {
+    let target = Duration::from_millis(500);
+    let start = Instant::now();
+    let mut iterations = 0u64;
+    while start.elapsed() < target {
+        for _ in 0..100 {
+            f();
+        }
+        iterations += 100;
+    }
+    let nanos = start.elapsed().as_nanos() as f64 / iterations as f64;
+    println!("{:<32} {:>10.1} ns/iter", name, nanos);
+}
+
+fn main() {
+    let squares: Vec<Square> = (0..64).map(|i| Square::from_index(i % 8, i / 8)).collect();
+    for (name, fen) in POSITIONS {
+        let board = Game::from_fen(fen).unwrap().get_board().clone();
+        let vec_board = VecBoard::new(&board);
+
+        bench(&format!("{} get/bitboard", name), || {
+            for square in &squares {
+                black_box(board.get(black_box(square)));
+            }
+        });
+        bench(&format!("{} get/vec", name), || {
+            for square in &squares {
+                black_box(vec_board.get(black_box(square)));
+            }
+        });
+
+        bench(&format!("{} get_offset/bitboard", name), || {
+            for square in &squares {
+                for (file_offset, rank_offset) in KNIGHT_JUMPS {
+                    black_box(board.get_offset(square, file_offset, rank_offset));
+                }
+            }
+        });
+        bench(&format!("{} get_offset/vec", name), || {
+            for square in &squares {
+                for (file_offset, rank_offset) in KNIGHT_JUMPS {
+                    black_box(vec_board.get_offset(square, file_offset, rank_offset));
+                }
+            }
+        });
+
+        bench(&format!("{} get_all_pieces/bitboard", name), || {
+            black_box(black_box(&board).get_all_pieces());
+        });
+        bench(&format!("{} get_all_pieces/vec", name), || {
+            black_box(black_box(&vec_board).get_all_pieces());
+        });
+    }
+}
@@ -1,14 +1,23 @@
 use std::collections::HashMap;
 
+pub mod attacks;
 pub mod board;
+pub mod castling;
+pub mod draws;
+pub mod fen;
 pub mod game;
 pub mod moves;
+pub mod perft;
+pub mod pgn;
 pub mod pieces;
+pub(crate) mod random;
+pub mod san;
 pub mod squares;
+pub mod uci;
+pub mod zobrist;
 
 pub use board::Board;
-use game::move_;
-pub use game::{Color, Game};
+pub use game::{Color, Game, GameStatus};
 pub use moves::Move;
 use pieces::piece;
 pub use pieces::{Piece, PieceType};
@@ -16,7 +25,7 @@ use squares::square;
 pub use squares::{File, Rank, Square};
 
 pub fn generate_starting_position() -> HashMap<Square, Piece> {
-    return HashMap::from([
+    HashMap::from([
         (square!(A 1), piece!(White, Rook)),
         (square!(B 1), piece!(White, Knight)),
         (square!(C 1), piece!(White, Bishop)),
@@ -49,5 +58,5 @@ pub fn generate_starting_position() -> HashMap<Square, Piece> {
         (square!(F 7), piece!(Black, Pawn)),
         (square!(G 7), piece!(Black, Pawn)),
         (square!(H 7), piece!(Black, Pawn)),
-    ]);
+    ])
 }
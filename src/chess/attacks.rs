@@ -0,0 +1,407 @@
+use std::sync::OnceLock;
+
+use super::{Color, Square};
+
+const KNIGHT_JUMPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_STEPS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+const DIAGONALS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const STRAIGHTS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+// Magic numbers for each square, found by trying random numbers with few bits set
+// until one sent every arrangement of blockers to a slot with the right attacks
+const BISHOP_MAGICS: [u64; 64] = [
+    0x1002200101020088,
+    0x4004010404049080,
+    0x200404008a020422,
+    0x000806004c002202,
+    0x8001104110000090,
+    0x8002080248000202,
+    0x0000440484408000,
+    0x0002010082012020,
+    0x0488406912288201,
+    0x0101600202004110,
+    0x0810100400842440,
+    0x0800240410822002,
+    0x0000020210010030,
+    0x8100008260210008,
+    0x4404004404044201,
+    0x2440008084104200,
+    0x88220a08207c0280,
+    0x4104801001080120,
+    0x4110020808304010,
+    0x2518050402400980,
+    0x0002000422010010,
+    0x0403020080a00100,
+    0x0244200200822880,
+    0x0608200200820860,
+    0x81202818c6100400,
+    0x4081501088100100,
+    0x2002491448020400,
+    0x0070040000440008,
+    0x0880820004010400,
+    0x8490108202c80400,
+    0x0004004204050c41,
+    0x800102400c241402,
+    0x1014200a00041013,
+    0x0008110400300400,
+    0x0042060900220800,
+    0x00d2200800010105,
+    0x0c0a0484008a0020,
+    0x801001010802100c,
+    0x0919920202440101,
+    0x06020a0202604050,
+    0x4202083240260821,
+    0x0118520260221008,
+    0x00000c4402021001,
+    0x1010006018020100,
+    0x9600204c10130500,
+    0x00c0810403000020,
+    0x0002640420801401,
+    0x8001040400900840,
+    0x1002221004048000,
+    0x0020808808420401,
+    0x0100010080908040,
+    0x2000800884040820,
+    0x1000002020444b28,
+    0x01180890d0008888,
+    0x4090448800940100,
+    0x0160380a00404240,
+    0x5002042109101080,
+    0x800800220d300800,
+    0x0808200840441004,
+    0x8008022006150402,
+    0x2000000070020213,
+    0x0202414119211100,
+    0x8820620204180484,
+    0x8012100115010208,
+];
+const ROOK_MAGICS: [u64; 64] = [
+    0x0880102080004000,
+    0x8340004410002000,
+    0x0880200090008268,
+    0x0080080080100004,
+    0x8100110004020800,
+    0x0300010004000822,
+    0x08801a0029000080,
+    0x8100050001204882,
+    0x0844800081400320,
+    0x0804402010004000,
+    0x0108802003100480,
+    0x0004808008001000,
+    0x0003001801001014,
+    0x0002000200041008,
+    0x0004008108042210,
+    0x0105000100009042,
+    0x0400808000400021,
+    0xc100404010002000,
+    0x0060008010002088,
+    0x0400808008001000,
+    0x4440808008000400,
+    0x1002008004000280,
+    0x40024400300d1248,
+    0x0010020000408104,
+    0x0101008200204200,
+    0x8020002040005000,
+    0x4100100080802000,
+    0x4008006a80100280,
+    0x1020080080040080,
+    0x0004010040020040,
+    0x0018a12400080290,
+    0x6140004200008104,
+    0x4000400020800090,
+    0x2020002080804000,
+    0x0000408202002010,
+    0x0080100501000820,
+    0x0000800400800800,
+    0x000a200408014010,
+    0x0100800200800100,
+    0xa00800570200008c,
+    0x008000406000c010,
+    0x1040100028002000,
+    0x0048200100110040,
+    0x0068490210030020,
+    0x1009080005010010,
+    0x2142000804010100,
+    0x1001080110840002,
+    0x1801004400820001,
+    0x010440208d020200,
+    0x0000400020008080,
+    0x0200200080100280,
+    0x0000100020090100,
+    0x0204008008020480,
+    0x8104010040020040,
+    0x78000201b0080400,
+    0x0040800051002880,
+    0x0050108001002041,
+    0x208a801100614003,
+    0x0006002042089082,
+    0x0011090004201001,
+    0x1002001004200802,
+    0x0005000208040001,
+    0x0002002701ac0822,
+    0x000010250184004a,
+];
+
+// Squares a piece on each square attacks, indexed by bit index
+struct Tables {
+    knight: [u64; 64],
+    king: [u64; 64],
+    // Indexed by Color, as pawns attack towards the opponent
+    pawn: [[u64; 64]; 2],
+    bishop: SliderTable,
+    rook: SliderTable,
+}
+
+// Attacks for a sliding piece looked up by the pieces in its way.
+// The blockers that matter are multiplied by a magic number which maps
+// every arrangement of them to a distinct slot in the attack table
+struct SliderTable {
+    magics: [Magic; 64],
+    attacks: Vec<u64>,
+}
+
+#[derive(Copy, Clone, Default)]
+struct Magic {
+    // The squares whose blockers change the attacks, which leaves out the edges
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    // Where this square's slots start in the attack table
+    offset: usize,
+}
+
+impl Magic {
+    fn get_index(&self, occupied: u64) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+impl SliderTable {
+    fn new(directions: &[(i8, i8); 4], magic_numbers: &[u64; 64]) -> SliderTable {
+        let mut magics = [Magic::default(); 64];
+        let mut attacks = Vec::new();
+        for (bit_index, magic) in magics.iter_mut().enumerate() {
+            let mask = get_ray_mask(bit_index, directions);
+            let bits = mask.count_ones();
+            *magic = Magic {
+                mask,
+                magic: magic_numbers[bit_index],
+                shift: 64 - bits,
+                offset: attacks.len(),
+            };
+            let mut table = vec![None; 1 << bits];
+            // Visit every subset of the mask, i.e. every arrangement of blockers
+            let mut blockers = 0u64;
+            loop {
+                let attack = get_ray_attacks(bit_index, directions, blockers);
+                let slot = &mut table[magic.get_index(blockers) - magic.offset];
+                // Different blockers may share a slot only if they give the same attacks
+                assert!(
+                    slot.is_none_or(|existing| existing == attack),
+                    "Magic number for bit {} maps different attacks to the same slot",
+                    bit_index
+                );
+                *slot = Some(attack);
+                blockers = blockers.wrapping_sub(mask) & mask;
+                if blockers == 0 {
+                    break;
+                }
+            }
+            attacks.extend(table.into_iter().map(|attack| attack.unwrap_or(0)));
+        }
+        SliderTable { magics, attacks }
+    }
+
+    fn get(&self, bit_index: usize, occupied: u64) -> u64 {
+        self.attacks[self.magics[bit_index].get_index(occupied)]
+    }
+}
+
+fn get_offset_bit(bit_index: usize, file_offset: i8, rank_offset: i8) -> Option<usize> {
+    let file_index = (bit_index % 8) as i8 + file_offset;
+    let rank_index = (bit_index / 8) as i8 + rank_offset;
+    if (0..8).contains(&file_index) && (0..8).contains(&rank_index) {
+        Some((rank_index * 8 + file_index) as usize)
+    } else {
+        None
+    }
+}
+
+fn get_step_attacks(bit_index: usize, steps: &[(i8, i8)]) -> u64 {
+    steps
+        .iter()
+        .filter_map(|(file_offset, rank_offset)| {
+            get_offset_bit(bit_index, *file_offset, *rank_offset)
+        })
+        .fold(0, |attacks, to| attacks | 1 << to)
+}
+
+// Slide out from a square in each direction, stopping at (and including) the first blocker
+fn get_ray_attacks(bit_index: usize, directions: &[(i8, i8); 4], occupied: u64) -> u64 {
+    let mut attacks = 0;
+    for (file_direction, rank_direction) in directions {
+        let mut current = bit_index;
+        while let Some(next) = get_offset_bit(current, *file_direction, *rank_direction) {
+            attacks |= 1 << next;
+            if occupied & 1 << next != 0 {
+                break;
+            }
+            current = next;
+        }
+    }
+    attacks
+}
+
+// The squares along each ray that could block it, not counting the last square
+// before the edge, as a piece there blocks nothing further along
+fn get_ray_mask(bit_index: usize, directions: &[(i8, i8); 4]) -> u64 {
+    let mut mask = 0;
+    for (file_direction, rank_direction) in directions {
+        let mut current = bit_index;
+        while let Some(next) = get_offset_bit(current, *file_direction, *rank_direction) {
+            if get_offset_bit(next, *file_direction, *rank_direction).is_none() {
+                break;
+            }
+            mask |= 1 << next;
+            current = next;
+        }
+    }
+    mask
+}
+
+fn get_tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| Tables {
+        knight: std::array::from_fn(|i| get_step_attacks(i, &KNIGHT_JUMPS)),
+        king: std::array::from_fn(|i| get_step_attacks(i, &KING_STEPS)),
+        pawn: [
+            std::array::from_fn(|i| get_step_attacks(i, &[(-1, 1), (1, 1)])),
+            std::array::from_fn(|i| get_step_attacks(i, &[(-1, -1), (1, -1)])),
+        ],
+        bishop: SliderTable::new(&DIAGONALS, &BISHOP_MAGICS),
+        rook: SliderTable::new(&STRAIGHTS, &ROOK_MAGICS),
+    })
+}
+
+pub fn get_knight_attacks(square: &Square) -> u64 {
+    get_tables().knight[square.index()]
+}
+
+pub fn get_king_attacks(square: &Square) -> u64 {
+    get_tables().king[square.index()]
+}
+
+// The squares a pawn of the given colour on the square could capture on
+pub fn get_pawn_attacks(color: Color, square: &Square) -> u64 {
+    get_tables().pawn[color as usize][square.index()]
+}
+
+// Sliding attacks include the first piece in each direction, whichever colour it is
+pub fn get_bishop_attacks(square: &Square, occupied: u64) -> u64 {
+    get_tables().bishop.get(square.index(), occupied)
+}
+
+pub fn get_rook_attacks(square: &Square, occupied: u64) -> u64 {
+    get_tables().rook.get(square.index(), occupied)
+}
+
+pub fn get_queen_attacks(square: &Square, occupied: u64) -> u64 {
+    get_bishop_attacks(square, occupied) | get_rook_attacks(square, occupied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            board::{get_bit, get_squares},
+            random::Random,
+            square, File, Rank,
+        },
+        *,
+    };
+
+    fn bits(squares: &[Square]) -> u64 {
+        squares
+            .iter()
+            .fold(0, |bits, square| bits | get_bit(square))
+    }
+
+    #[test]
+    fn knight_attacks() {
+        assert!(get_knight_attacks(&square!(A 1)) == bits(&[square!(B 3), square!(C 2)]));
+        assert!(get_knight_attacks(&square!(E 4)).count_ones() == 8);
+    }
+
+    #[test]
+    fn king_attacks() {
+        let expected = bits(&[square!(G 8), square!(G 7), square!(H 7)]);
+        assert!(get_king_attacks(&square!(H 8)) == expected);
+    }
+
+    #[test]
+    fn pawn_attacks() {
+        assert!(get_pawn_attacks(Color::White, &square!(A 2)) == get_bit(&square!(B 3)));
+        let expected = bits(&[square!(D 6), square!(F 6)]);
+        assert!(get_pawn_attacks(Color::Black, &square!(E 7)) == expected);
+    }
+
+    #[test]
+    fn slider_attacks_stop_at_blockers() {
+        let occupied = bits(&[square!(D 4), square!(D 6), square!(F 4), square!(B 2)]);
+        let expected = bits(&[
+            square!(D 5),
+            square!(D 6),
+            square!(D 3),
+            square!(D 2),
+            square!(D 1),
+            square!(E 4),
+            square!(F 4),
+            square!(C 4),
+            square!(B 4),
+            square!(A 4),
+        ]);
+        assert!(get_rook_attacks(&square!(D 4), occupied) == expected);
+        let diagonal = get_bishop_attacks(&square!(D 4), occupied);
+        assert!(diagonal & get_bit(&square!(B 2)) != 0);
+        assert!(diagonal & get_bit(&square!(A 1)) == 0);
+        assert!(diagonal.count_ones() == 12);
+    }
+
+    // Check the magic lookups against walking the rays for many random arrangements
+    #[test]
+    fn magics_match_ray_walk() {
+        let mut random = Random::new(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..200 {
+            let occupied = random.next() & random.next();
+            for square in get_squares(u64::MAX) {
+                let bit_index = square.index();
+                assert!(
+                    get_bishop_attacks(&square, occupied)
+                        == get_ray_attacks(bit_index, &DIAGONALS, occupied)
+                );
+                assert!(
+                    get_rook_attacks(&square, occupied)
+                        == get_ray_attacks(bit_index, &STRAIGHTS, occupied)
+                );
+            }
+        }
+    }
+}
@@ -1,40 +1,110 @@
-use crate::chess::{File, Piece, Rank, Square};
-use enum_iterator::{all, cardinality};
+use crate::chess::{attacks, zobrist, Color, Piece, PieceType, Square};
 use std::collections::HashMap;
 
-#[derive(Debug)]
+const WIDTH: usize = 8;
+const HEIGHT: usize = 8;
+
+// Pieces are kept both as bitboards, with bit (rank index * 8 + file index) set
+// for each occupied square, and as a plain array so lookups can return a reference
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Board {
-    squares: Vec<Vec<Option<Piece>>>,
+    // One set for each piece type, in PieceType order, covering both colours
+    piece_types: [u64; 6],
+    // One set for each colour, in Color order
+    colors: [u64; 2],
+    squares: [Option<Piece>; WIDTH * HEIGHT],
+    // The Zobrist key of the pieces, kept up to date as they move
+    zobrist_key: u64,
+}
+
+pub fn get_bit(square: &Square) -> u64 {
+    1 << square.index()
+}
+
+// Iterate over the squares in a bitboard, lowest bit first
+pub fn get_squares(mut bitboard: u64) -> impl Iterator<Item = Square> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+        let bit_index = bitboard.trailing_zeros() as usize;
+        bitboard &= bitboard - 1;
+        Some(Square::from_square_index(bit_index))
+    })
 }
 
 impl Board {
     pub fn new(starting_position: HashMap<Square, Piece>) -> Board {
         let mut board = Board {
-            squares: vec![vec![None; cardinality::<File>()]; cardinality::<Rank>()],
+            piece_types: [0; 6],
+            colors: [0; 2],
+            squares: [None; WIDTH * HEIGHT],
+            zobrist_key: 0,
         };
-        for (i, file) in all::<File>().enumerate() {
-            for (j, rank) in all::<Rank>().enumerate() {
-                let square = Square::new(file, rank);
-                if let Some(piece) = starting_position.get(&square) {
-                    board.squares[i][j] = Some(*piece);
-                }
-            }
+        for (square, piece) in starting_position {
+            board.set(&square, Some(piece));
         }
         board
     }
 
     pub fn get_width(&self) -> usize {
-        self.squares[0].len()
+        WIDTH
     }
 
     pub fn get_height(&self) -> usize {
-        self.squares.len()
+        HEIGHT
     }
 
     // Index is guaranteed to be in bounds due to the coupling between
-    // the Square/File/Rank and the Board initialisation
+    // the Square/File/Rank and the Board size
     pub fn get(&self, square: &Square) -> Option<&Piece> {
-        self.squares[square.get_file_index()][square.get_rank_index()].as_ref()
+        self.squares[square.index()].as_ref()
+    }
+
+    // Place a piece on (or clear) a square, returning whatever was there before
+    pub fn set(&mut self, square: &Square, piece: Option<Piece>) -> Option<Piece> {
+        let bit_index = square.index();
+        let bit = 1 << bit_index;
+        let previous = std::mem::replace(&mut self.squares[bit_index], piece);
+        if let Some(previous) = previous {
+            self.piece_types[previous.piece_type as usize] &= !bit;
+            self.colors[previous.color as usize] &= !bit;
+            self.zobrist_key ^= zobrist::get_piece_key(previous, square);
+        }
+        if let Some(piece) = piece {
+            self.piece_types[piece.piece_type as usize] |= bit;
+            self.colors[piece.color as usize] |= bit;
+            self.zobrist_key ^= zobrist::get_piece_key(piece, square);
+        }
+        previous
+    }
+
+    // Move the piece on one square to another, returning any piece that was taken
+    pub fn move_piece(&mut self, from: &Square, to: &Square) -> Option<Piece> {
+        let piece = self.set(from, None);
+        self.set(to, piece)
+    }
+
+    pub fn get_zobrist_key(&self) -> u64 {
+        self.zobrist_key
+    }
+
+    // Every square with a piece of the given type, of either colour
+    pub fn get_piece_type_bitboard(&self, piece_type: PieceType) -> u64 {
+        self.piece_types[piece_type as usize]
+    }
+
+    // Every square with a piece of the given colour
+    pub fn get_color_bitboard(&self, color: Color) -> u64 {
+        self.colors[color as usize]
+    }
+
+    pub fn get_piece_bitboard(&self, piece: Piece) -> u64 {
+        self.get_piece_type_bitboard(piece.piece_type) & self.get_color_bitboard(piece.color)
+    }
+
+    pub fn get_occupied_bitboard(&self) -> u64 {
+        self.colors[0] | self.colors[1]
     }
 
     // Get the square and piece some given offset from a valid square
@@ -45,18 +115,14 @@ impl Board {
         file_offset: i8,
         rank_offset: i8,
     ) -> Option<(Square, Option<&Piece>)> {
-        let file_index = square.get_file_index() as i8 + file_offset;
-        let rank_index = square.get_rank_index() as i8 + rank_offset;
-        if file_index < 0 || rank_index < 0 {
-            return None;
-        }
-        let square = self.validate_square(file_index as usize, rank_index as usize);
-        square.map(|square| (square, self.get(&square)))
+        square
+            .offset(file_offset, rank_offset)
+            .map(|square| (square, self.get(&square)))
     }
 
     // Check if a square index is without the bounds of the board
     pub fn validate_square(&self, file_index: usize, rank_index: usize) -> Option<Square> {
-        if rank_index < self.squares.len() && file_index < self.squares[rank_index].len() {
+        if rank_index < HEIGHT && file_index < WIDTH {
             Some(Square::from_index(file_index, rank_index))
         } else {
             None
@@ -64,21 +130,117 @@ impl Board {
     }
 
     pub fn get_all_squares(&self) -> Vec<(Square, Option<&Piece>)> {
-        let mut pieces = Vec::new();
-        for file in all::<File>() {
-            for rank in all::<Rank>() {
-                let square = Square::new(file, rank);
-                pieces.push((square, self.get(&square)));
-            }
-        }
-        pieces
+        (0..WIDTH * HEIGHT)
+            .map(|bit_index| {
+                (
+                    Square::from_square_index(bit_index),
+                    self.squares[bit_index].as_ref(),
+                )
+            })
+            .collect()
     }
 
     pub fn get_all_pieces(&self) -> Vec<(Square, &Piece)> {
-        self.get_all_squares()
-            .iter()
-            .filter(|(_, piece)| piece.is_some())
-            .map(|(square, piece)| (*square, piece.unwrap()))
+        get_squares(self.get_occupied_bitboard())
+            .map(|square| (square, self.squares[square.index()].as_ref().unwrap()))
             .collect()
     }
+
+    // Check if any piece of the given color could take on a square,
+    // regardless of whether the move would leave its own king in check
+    pub fn is_square_attacked(&self, square: &Square, by: Color) -> bool {
+        let attackers = |piece_type: PieceType| {
+            self.get_piece_bitboard(Piece {
+                piece_type,
+                color: by,
+            })
+        };
+        let occupied = self.get_occupied_bitboard();
+        let diagonal = attackers(PieceType::Bishop) | attackers(PieceType::Queen);
+        let straight = attackers(PieceType::Rook) | attackers(PieceType::Queen);
+        // Attacks go both ways, so look for attackers from the square itself.
+        // Pawns attack forwards, so that means looking as a pawn of the other colour
+        attacks::get_pawn_attacks(by.opposite(), square) & attackers(PieceType::Pawn) != 0
+            || attacks::get_knight_attacks(square) & attackers(PieceType::Knight) != 0
+            || attacks::get_king_attacks(square) & attackers(PieceType::King) != 0
+            || attacks::get_bishop_attacks(square, occupied) & diagonal != 0
+            || attacks::get_rook_attacks(square, occupied) & straight != 0
+    }
+
+    pub fn find_king(&self, color: Color) -> Option<Square> {
+        get_squares(self.get_piece_bitboard(Piece {
+            piece_type: PieceType::King,
+            color,
+        }))
+        .next()
+    }
+
+    pub fn is_in_check(&self, color: Color) -> bool {
+        self.find_king(color)
+            .is_some_and(|king| self.is_square_attacked(&king, color.opposite()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{piece, square, File, Game, Rank},
+        *,
+    };
+
+    fn kiwipete() -> Board {
+        Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap()
+            .get_board()
+            .clone()
+    }
+
+    #[test]
+    fn bitboards_follow_pieces() {
+        let mut board = Board::new(HashMap::from([
+            (square!(A 1), piece!(White, Rook)),
+            (square!(H 8), piece!(Black, Rook)),
+        ]));
+        assert!(board.get_piece_type_bitboard(PieceType::Rook) == 1 | 1 << 63);
+        assert!(board.get_color_bitboard(Color::Black) == 1 << 63);
+
+        let taken = board.move_piece(&square!(A 1), &square!(H 8));
+        assert!(taken == Some(piece!(Black, Rook)));
+        assert!(board.get(&square!(A 1)).is_none());
+        assert!(board.get_piece_bitboard(piece!(White, Rook)) == 1 << 63);
+        assert!(board.get_color_bitboard(Color::Black) == 0);
+        assert!(board.get_occupied_bitboard() == 1 << 63);
+    }
+
+    #[test]
+    fn bitboards_match_squares() {
+        let board = kiwipete();
+        for (square, piece) in board.get_all_squares() {
+            let bit = get_bit(&square);
+            assert!((board.get_occupied_bitboard() & bit != 0) == piece.is_some());
+            if let Some(piece) = piece {
+                assert!(board.get_piece_bitboard(*piece) & bit != 0);
+            }
+        }
+        assert!(board.get_all_pieces().len() == 32);
+        assert!(board.get_occupied_bitboard().count_ones() == 32);
+    }
+
+    #[test]
+    fn offsets() {
+        let board = kiwipete();
+        assert!(board.get_offset(&square!(A 1), -1, 0).is_none());
+        assert!(board.get_offset(&square!(H 8), 0, 1).is_none());
+        let (square, piece) = board.get_offset(&square!(E 1), 1, 2).unwrap();
+        assert!(square == square!(F 3));
+        assert!(piece == Some(&piece!(White, Queen)));
+    }
+
+    #[test]
+    fn squares_in_bitboard() {
+        let squares: Vec<Square> =
+            get_squares(get_bit(&square!(C 2)) | get_bit(&square!(B 7))).collect();
+        assert!(squares == vec![square!(C 2), square!(B 7)]);
+        assert!(kiwipete().find_king(Color::Black) == Some(square!(E 8)));
+    }
 }
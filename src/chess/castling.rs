@@ -0,0 +1,106 @@
+use super::{moves::CastleType, Board, Color, File, Piece, PieceType, Rank, Square};
+
+// The squares a king and rook move between when castling
+pub struct CastleSquares {
+    pub king_from: Square,
+    pub king_to: Square,
+    pub rook_from: Square,
+    pub rook_to: Square,
+}
+
+impl CastleSquares {
+    pub fn new(color: Color, castle_type: CastleType) -> CastleSquares {
+        let rank = match color {
+            Color::White => Rank::One,
+            Color::Black => Rank::Eight,
+        };
+        let (king_to, rook_from, rook_to) = match castle_type {
+            CastleType::Short => (File::G, File::H, File::F),
+            CastleType::Long => (File::C, File::A, File::D),
+        };
+        CastleSquares {
+            king_from: Square::new(File::E, rank),
+            king_to: Square::new(king_to, rank),
+            rook_from: Square::new(rook_from, rank),
+            rook_to: Square::new(rook_to, rank),
+        }
+    }
+
+    // Whether the king and rook are both still on the squares they castle from
+    pub fn are_pieces_home(&self, board: &Board, color: Color) -> bool {
+        let is_home = |square: &Square, piece_type: PieceType| {
+            board.get(square) == Some(&Piece { piece_type, color })
+        };
+        is_home(&self.king_from, PieceType::King) && is_home(&self.rook_from, PieceType::Rook)
+    }
+
+    // Squares that must be empty for the king and rook to pass each other
+    pub fn get_empty_squares(&self) -> Vec<Square> {
+        let (king, rook) = (self.king_from.file_index(), self.rook_from.file_index());
+        self.get_squares_between(king.min(rook) + 1, king.max(rook) - 1)
+    }
+
+    // Squares the king starts on, passes through and lands on,
+    // none of which may be attacked
+    pub fn get_king_path(&self) -> Vec<Square> {
+        let (from, to) = (self.king_from.file_index(), self.king_to.file_index());
+        self.get_squares_between(from.min(to), from.max(to))
+    }
+
+    fn get_squares_between(&self, start: usize, end: usize) -> Vec<Square> {
+        (start..=end)
+            .map(|file_index| Square::from_index(file_index, self.king_from.rank_index()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CastlingRights {
+    pub white_short: bool,
+    pub white_long: bool,
+    pub black_short: bool,
+    pub black_long: bool,
+}
+
+impl CastlingRights {
+    pub fn none() -> CastlingRights {
+        CastlingRights {
+            white_short: false,
+            white_long: false,
+            black_short: false,
+            black_long: false,
+        }
+    }
+
+    pub fn get(&self, color: Color, castle_type: CastleType) -> bool {
+        match (color, castle_type) {
+            (Color::White, CastleType::Short) => self.white_short,
+            (Color::White, CastleType::Long) => self.white_long,
+            (Color::Black, CastleType::Short) => self.black_short,
+            (Color::Black, CastleType::Long) => self.black_long,
+        }
+    }
+
+    pub fn set(&mut self, color: Color, castle_type: CastleType, value: bool) {
+        let right = match (color, castle_type) {
+            (Color::White, CastleType::Short) => &mut self.white_short,
+            (Color::White, CastleType::Long) => &mut self.white_long,
+            (Color::Black, CastleType::Short) => &mut self.black_short,
+            (Color::Black, CastleType::Long) => &mut self.black_long,
+        };
+        *right = value;
+    }
+
+    // A king or rook leaving its starting square, or a rook being taken on it,
+    // loses the castling rights that depend on that piece
+    pub fn update(&mut self, square: &Square) {
+        for color in [Color::White, Color::Black] {
+            for castle_type in [CastleType::Short, CastleType::Long] {
+                let castle_squares = CastleSquares::new(color, castle_type);
+                if *square == castle_squares.king_from || *square == castle_squares.rook_from {
+                    self.set(color, castle_type, false);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+
+use super::{Board, Piece, PieceType, Square};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DrawReason {
+    // Claimable once 50 moves pass by each player without a capture or pawn move
+    FiftyMoveRule,
+    // Automatic once 75 moves pass by each player without a capture or pawn move
+    SeventyFiveMoveRule,
+    // Claimable once the same position occurs three times
+    ThreefoldRepetition,
+    // Automatic once the same position occurs five times
+    FivefoldRepetition,
+    // Automatic when neither player could ever deliver checkmate
+    InsufficientMaterial,
+    // One player accepted the other's draw offer
+    Agreement,
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use DrawReason::*;
+        match self {
+            FiftyMoveRule => write!(f, "the fifty-move rule"),
+            SeventyFiveMoveRule => write!(f, "the seventy-five-move rule"),
+            ThreefoldRepetition => write!(f, "threefold repetition"),
+            FivefoldRepetition => write!(f, "fivefold repetition"),
+            InsufficientMaterial => write!(f, "insufficient material"),
+            Agreement => write!(f, "agreement"),
+        }
+    }
+}
+
+// Check if no sequence of legal moves could lead to checkmate: bare kings,
+// a single minor piece, or any number of bishops that all stand on the same colour
+pub fn has_insufficient_material(board: &Board) -> bool {
+    let others: Vec<(Square, &Piece)> = board
+        .get_all_pieces()
+        .into_iter()
+        .filter(|(_, piece)| piece.piece_type != PieceType::King)
+        .collect();
+    match others[..] {
+        [] => true,
+        [(_, piece)] => matches!(piece.piece_type, PieceType::Knight | PieceType::Bishop),
+        [(first, _), ..] => others.iter().all(|(square, piece)| {
+            piece.piece_type == PieceType::Bishop
+                && square.is_light_square() == first.is_light_square()
+        }),
+    }
+}
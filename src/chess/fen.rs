@@ -0,0 +1,543 @@
+use std::{collections::HashMap, fmt};
+
+use super::{
+    castling::{CastleSquares, CastlingRights},
+    moves::CastleType,
+    Board, Color, File, Game, Piece, PieceType, Rank, Square,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FenField {
+    Placement,
+    SideToMove,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveNumber,
+}
+
+impl fmt::Display for FenField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use FenField::*;
+        let field = match self {
+            Placement => "piece placement",
+            SideToMove => "side to move",
+            Castling => "castling",
+            EnPassant => "en passant",
+            HalfmoveClock => "halfmove clock",
+            FullmoveNumber => "fullmove number",
+        };
+        write!(f, "{}", field)
+    }
+}
+
+const FIELDS: [FenField; 6] = [
+    FenField::Placement,
+    FenField::SideToMove,
+    FenField::Castling,
+    FenField::EnPassant,
+    FenField::HalfmoveClock,
+    FenField::FullmoveNumber,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FenError {
+    // The field is missing or empty
+    MissingField(FenField),
+    // More than six space separated fields, starting at the given position
+    TooManyFields(usize),
+    // A character that does not belong at the given position of the FEN
+    InvalidCharacter(FenField, usize, char),
+    // The field ended before it described everything it needs to
+    UnexpectedEnd(FenField),
+    // The placement doesn't give the player exactly one king
+    KingCount(Color),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use FenError::*;
+        match self {
+            MissingField(field) => write!(f, "Missing {} field", field),
+            TooManyFields(i) => write!(f, "Unexpected extra field at position {}", i),
+            InvalidCharacter(field, i, c) => write!(
+                f,
+                "Invalid character {} at position {} in the {} field",
+                c, i, field
+            ),
+            UnexpectedEnd(field) => write!(f, "The {} field ended unexpectedly", field),
+            KingCount(color) => write!(f, "{} must have exactly one king", color),
+        }
+    }
+}
+
+impl Game {
+    // Set up a game from Forsyth-Edwards Notation, e.g. the starting position is
+    // rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1
+    pub fn from_fen(input: &str) -> Result<Game, FenError> {
+        let input = input.trim_end();
+        // Split into fields, keeping track of where each one starts for error positions
+        let mut fields = Vec::new();
+        let mut start = 0;
+        for part in input.split(' ') {
+            fields.push((start, part));
+            start += part.len() + 1;
+        }
+        if fields.len() > FIELDS.len() {
+            return Err(FenError::TooManyFields(fields[FIELDS.len()].0));
+        }
+        for (i, field) in FIELDS.iter().enumerate() {
+            if fields.get(i).is_none_or(|(_, part)| part.is_empty()) {
+                return Err(FenError::MissingField(*field));
+            }
+        }
+
+        let board = parse_placement(fields[0])?;
+        for color in [Color::White, Color::Black] {
+            let king = Piece {
+                piece_type: PieceType::King,
+                color,
+            };
+            if board.get_piece_bitboard(king).count_ones() != 1 {
+                return Err(FenError::KingCount(color));
+            }
+        }
+        let turn = parse_side_to_move(fields[1])?;
+        // Other tools often write rights or en passant squares that don't fit the placement,
+        // so drop those rather than rejecting the whole record
+        let castling_rights = clear_missing_castling_rights(parse_castling(fields[2])?, &board);
+        let en_passant = parse_en_passant(fields[3], turn)?
+            .filter(|square| has_en_passant_pawn(&board, turn, square));
+        let halfmove_clock = parse_number(fields[4], FenField::HalfmoveClock)?;
+        let fullmove_number = parse_number(fields[5], FenField::FullmoveNumber)?;
+        if fullmove_number == 0 {
+            return Err(FenError::InvalidCharacter(
+                FenField::FullmoveNumber,
+                fields[5].0,
+                '0',
+            ));
+        }
+
+        Ok(Game::from_position(
+            board,
+            turn,
+            castling_rights,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+        ))
+    }
+}
+
+impl Game {
+    pub fn to_fen(&self) -> String {
+        let turn = match self.get_turn() {
+            Color::White => 'w',
+            Color::Black => 'b',
+        };
+        let castling_rights = self.get_castling_rights();
+        let castling: String = [
+            (castling_rights.white_short, 'K'),
+            (castling_rights.white_long, 'Q'),
+            (castling_rights.black_short, 'k'),
+            (castling_rights.black_long, 'q'),
+        ]
+        .iter()
+        .filter(|(right, _)| *right)
+        .map(|(_, c)| c)
+        .collect();
+        let en_passant = self
+            .get_en_passant()
+            .map_or(String::from("-"), |square| square.to_string());
+        format!(
+            "{} {} {} {} {} {}",
+            self.get_board().to_fen(),
+            turn,
+            if castling.is_empty() { "-" } else { &castling },
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+}
+
+impl Board {
+    // Get just the piece placement field of a FEN record
+    pub fn to_fen(&self) -> String {
+        let mut ranks = Vec::new();
+        for rank_index in (0..self.get_height()).rev() {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for file_index in 0..self.get_width() {
+                match self.get(&Square::from_index(file_index, rank_index)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            rank.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        rank.push(piece_to_char(piece));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                rank.push_str(&empty.to_string());
+            }
+            ranks.push(rank);
+        }
+        ranks.join("/")
+    }
+}
+
+fn piece_to_char(piece: &Piece) -> char {
+    let c = piece.piece_type.to_char();
+    match piece.color {
+        Color::White => c,
+        Color::Black => c.to_ascii_lowercase(),
+    }
+}
+
+fn parse_placement((start, input): (usize, &str)) -> Result<Board, FenError> {
+    let field = FenField::Placement;
+    let mut position = HashMap::new();
+    // FEN lists the ranks from the eighth down to the first, each from the a-file to the h-file
+    let mut rank_index = 7;
+    let mut file_index = 0;
+    for (i, c) in input.chars().enumerate() {
+        let invalid = FenError::InvalidCharacter(field, start + i, c);
+        match c {
+            '/' => {
+                if file_index != 8 || rank_index == 0 {
+                    return Err(invalid);
+                }
+                rank_index -= 1;
+                file_index = 0;
+            }
+            '1'..='8' => {
+                file_index += c.to_digit(10).unwrap() as usize;
+                if file_index > 8 {
+                    return Err(invalid);
+                }
+            }
+            _ => {
+                let piece = piece_from_char(c).ok_or(invalid)?;
+                let file = File::from_index(file_index).ok_or(invalid)?;
+                let rank = Rank::from_index(rank_index).unwrap();
+                position.insert(Square::new(file, rank), piece);
+                file_index += 1;
+            }
+        }
+    }
+    if file_index != 8 || rank_index != 0 {
+        return Err(FenError::UnexpectedEnd(field));
+    }
+    Ok(Board::new(position))
+}
+
+fn piece_from_char(c: char) -> Option<Piece> {
+    let color = if c.is_ascii_uppercase() {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let piece_type = match c.to_ascii_uppercase() {
+        'P' => PieceType::Pawn,
+        c => PieceType::from_char(c)?,
+    };
+    Some(Piece { piece_type, color })
+}
+
+fn parse_side_to_move((start, input): (usize, &str)) -> Result<Color, FenError> {
+    match input {
+        "w" => Ok(Color::White),
+        "b" => Ok(Color::Black),
+        _ => {
+            // Point at the first character that stops this being a valid side
+            let (i, c) = input
+                .chars()
+                .enumerate()
+                .find(|(i, c)| *i > 0 || !matches!(c, 'w' | 'b'))
+                .unwrap();
+            Err(FenError::InvalidCharacter(
+                FenField::SideToMove,
+                start + i,
+                c,
+            ))
+        }
+    }
+}
+
+fn parse_castling((start, input): (usize, &str)) -> Result<CastlingRights, FenError> {
+    let field = FenField::Castling;
+    let mut castling_rights = CastlingRights::none();
+    if input == "-" {
+        return Ok(castling_rights);
+    }
+    for (i, c) in input.chars().enumerate() {
+        let right = match c {
+            'K' => &mut castling_rights.white_short,
+            'Q' => &mut castling_rights.white_long,
+            'k' => &mut castling_rights.black_short,
+            'q' => &mut castling_rights.black_long,
+            _ => return Err(FenError::InvalidCharacter(field, start + i, c)),
+        };
+        // Each right can only be given once
+        if *right {
+            return Err(FenError::InvalidCharacter(field, start + i, c));
+        }
+        *right = true;
+    }
+    Ok(castling_rights)
+}
+
+fn parse_en_passant(
+    (start, input): (usize, &str),
+    turn: Color,
+) -> Result<Option<Square>, FenError> {
+    let field = FenField::EnPassant;
+    if input == "-" {
+        return Ok(None);
+    }
+    // The en passant square is behind a pawn the opponent has just double pushed
+    let expected_rank = match turn {
+        Color::White => Rank::Six,
+        Color::Black => Rank::Three,
+    };
+    let mut chars = input.chars().enumerate();
+    let file = match chars.next() {
+        Some((i, c)) => {
+            File::from_char(c).ok_or(FenError::InvalidCharacter(field, start + i, c))?
+        }
+        None => return Err(FenError::UnexpectedEnd(field)),
+    };
+    let rank = match chars.next() {
+        Some((i, c)) => Rank::from_char(c)
+            .filter(|rank| *rank == expected_rank)
+            .ok_or(FenError::InvalidCharacter(field, start + i, c))?,
+        None => return Err(FenError::UnexpectedEnd(field)),
+    };
+    if let Some((i, c)) = chars.next() {
+        return Err(FenError::InvalidCharacter(field, start + i, c));
+    }
+    Ok(Some(Square::new(file, rank)))
+}
+
+// Keep only the castling rights whose king and rook are still on their starting squares
+fn clear_missing_castling_rights(
+    mut castling_rights: CastlingRights,
+    board: &Board,
+) -> CastlingRights {
+    for color in [Color::White, Color::Black] {
+        for castle_type in [CastleType::Short, CastleType::Long] {
+            if !CastleSquares::new(color, castle_type).are_pieces_home(board, color) {
+                castling_rights.set(color, castle_type, false);
+            }
+        }
+    }
+    castling_rights
+}
+
+// Check that an opponent's pawn could have just double pushed past the en passant square,
+// so it is in front of the square and the squares it passed through are empty
+fn has_en_passant_pawn(board: &Board, turn: Color, square: &Square) -> bool {
+    let direction = match turn {
+        Color::White => -1,
+        Color::Black => 1,
+    };
+    let pawn = Piece {
+        piece_type: PieceType::Pawn,
+        color: turn.opposite(),
+    };
+    let start = square.offset(0, -direction);
+    square
+        .offset(0, direction)
+        .is_some_and(|pawn_square| board.get(&pawn_square) == Some(&pawn))
+        && board.get(square).is_none()
+        && start.is_some_and(|start| board.get(&start).is_none())
+}
+
+fn parse_number((start, input): (usize, &str), field: FenField) -> Result<u32, FenError> {
+    if let Some((i, c)) = input.chars().enumerate().find(|(_, c)| !c.is_ascii_digit()) {
+        return Err(FenError::InvalidCharacter(field, start + i, c));
+    }
+    // Only fails on overflow, so point at the digit that made the number too large
+    input.parse().map_err(|_| {
+        let last = input.len() - 1;
+        FenError::InvalidCharacter(field, start + last, input.chars().last().unwrap())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::generate_starting_position, super::Move, *};
+
+    const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn starting_position() {
+        let game = Game::from_fen(STARTING_FEN).unwrap();
+        let expected = Game::new(generate_starting_position());
+        assert!(game.get_board() == expected.get_board());
+        assert!(game.get_turn() == Color::White);
+        assert!(game.get_castling_rights() == expected.get_castling_rights());
+        assert!(game.get_en_passant().is_none());
+        assert!(game.halfmove_clock == 0 && game.fullmove_number == 1);
+    }
+
+    #[test]
+    fn export_starting_position() {
+        let game = Game::new(generate_starting_position());
+        assert!(game.to_fen() == STARTING_FEN);
+        assert!(game.get_board().to_fen() == "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR");
+    }
+
+    #[test]
+    fn export_after_moves() {
+        let mut game = Game::new(generate_starting_position());
+        for input in ["e4", "c5", "Nf3"] {
+            game.do_move(Move::parse(input).unwrap()).unwrap();
+        }
+        let expected = "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2";
+        assert!(game.to_fen() == expected);
+    }
+
+    #[test]
+    fn round_trip() {
+        for fen in [
+            STARTING_FEN,
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b Kq e3 1 2",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 b - - 99 150",
+        ] {
+            assert!(Game::from_fen(fen).unwrap().to_fen() == fen);
+        }
+    }
+
+    #[test]
+    fn all_fields() {
+        let fen = "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b Kq e3 1 2";
+        let game = Game::from_fen(fen).unwrap();
+        assert!(game.get_turn() == Color::Black);
+        assert!(game.get_castling_rights().white_short && !game.get_castling_rights().white_long);
+        assert!(!game.get_castling_rights().black_short && game.get_castling_rights().black_long);
+        assert!(game.get_en_passant() == Some(Square::new(File::E, Rank::Three)));
+        assert!(game.halfmove_clock == 1 && game.fullmove_number == 2);
+        let knight = game
+            .get_board()
+            .get(&Square::new(File::F, Rank::Three))
+            .unwrap();
+        assert!(
+            *knight
+                == Piece {
+                    piece_type: PieceType::Knight,
+                    color: Color::White
+                }
+        );
+        assert!(game.get_board().get_all_pieces().len() == 32);
+    }
+
+    #[test]
+    fn missing_fields() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        let expected = FenError::MissingField(FenField::HalfmoveClock);
+        assert!(Game::from_fen(fen).err() == Some(expected));
+        let expected = FenError::MissingField(FenField::Placement);
+        assert!(Game::from_fen("").err() == Some(expected));
+    }
+
+    #[test]
+    fn too_many_fields() {
+        let fen = format!("{} 0", STARTING_FEN);
+        assert!(Game::from_fen(&fen).err() == Some(FenError::TooManyFields(57)));
+    }
+
+    #[test]
+    fn invalid_piece() {
+        let fen = "rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let expected = FenError::InvalidCharacter(FenField::Placement, 13, 'x');
+        assert!(Game::from_fen(fen).err() == Some(expected));
+    }
+
+    #[test]
+    fn rank_too_long() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR w KQkq - 0 1";
+        let expected = FenError::InvalidCharacter(FenField::Placement, 43, 'R');
+        assert!(Game::from_fen(fen).err() == Some(expected));
+        let fen = "rnbqkbnr/pppppppp/8/8/45/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let expected = FenError::InvalidCharacter(FenField::Placement, 23, '5');
+        assert!(Game::from_fen(fen).err() == Some(expected));
+    }
+
+    #[test]
+    fn rank_too_short() {
+        let fen = "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let expected = FenError::InvalidCharacter(FenField::Placement, 16, '/');
+        assert!(Game::from_fen(fen).err() == Some(expected));
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1";
+        let expected = FenError::UnexpectedEnd(FenField::Placement);
+        assert!(Game::from_fen(fen).err() == Some(expected));
+    }
+
+    #[test]
+    fn invalid_side_to_move() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1";
+        let expected = FenError::InvalidCharacter(FenField::SideToMove, 44, 'x');
+        assert!(Game::from_fen(fen).err() == Some(expected));
+    }
+
+    #[test]
+    fn invalid_castling() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkK - 0 1";
+        let expected = FenError::InvalidCharacter(FenField::Castling, 49, 'K');
+        assert!(Game::from_fen(fen).err() == Some(expected));
+    }
+
+    #[test]
+    fn invalid_en_passant() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e4 0 1";
+        let expected = FenError::InvalidCharacter(FenField::EnPassant, 54, '4');
+        assert!(Game::from_fen(fen).err() == Some(expected));
+    }
+
+    #[test]
+    fn wrong_king_count() {
+        let expected = FenError::KingCount(Color::White);
+        assert!(Game::from_fen("8/8/8/8/8/8/8/8 w - - 0 1").err() == Some(expected));
+        let expected = FenError::KingCount(Color::Black);
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_ok());
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/4K2k w - - 0 1").err() == Some(expected));
+        assert!(Game::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").err() == Some(expected));
+    }
+
+    #[test]
+    fn castling_rights_need_pieces_home() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K2N w KQkq - 0 1").unwrap();
+        assert!(game.get_castling_rights() == CastlingRights::none());
+        assert!(game.to_fen() == "4k3/8/8/8/8/8/8/4K2N w - - 0 1");
+        let game = Game::from_fen("r3k3/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert!(game.to_fen() == "r3k3/8/8/8/8/8/8/R3K2R w KQq - 0 1");
+    }
+
+    #[test]
+    fn en_passant_needs_pawn() {
+        let game = Game::from_fen("4k3/8/8/3P4/8/8/8/4K3 w - e6 0 1").unwrap();
+        assert!(game.get_en_passant().is_none());
+        assert!(game.legal_moves().len() == 6);
+        // The pawn can't have double pushed if something is on the square it came from
+        let game = Game::from_fen("4k3/4n3/8/3Pp3/8/8/8/4K3 w - e6 0 1").unwrap();
+        assert!(game.get_en_passant().is_none());
+        let game = Game::from_fen("4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1").unwrap();
+        assert!(game.get_en_passant() == Some(Square::new(File::E, Rank::Six)));
+    }
+
+    #[test]
+    fn invalid_clock() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - -1 1";
+        let expected = FenError::InvalidCharacter(FenField::HalfmoveClock, 53, '-');
+        assert!(Game::from_fen(fen).err() == Some(expected));
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0";
+        let expected = FenError::InvalidCharacter(FenField::FullmoveNumber, 55, '0');
+        assert!(Game::from_fen(fen).err() == Some(expected));
+    }
+}
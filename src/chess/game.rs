@@ -1,18 +1,14 @@
-use crate::chess::{Board, Piece, Square};
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
-#[macro_export]
-macro_rules! move_ {
-    ($from:expr, $to:expr) => {
-        Move {
-            from: $from,
-            to: $to,
-        }
-    };
-}
-pub(crate) use move_;
-
-use super::Move;
+use super::{
+    castling::{CastleSquares, CastlingRights},
+    draws::{self, DrawReason},
+    moves::{CastleType, Disambiguation, GameResult, MoveType},
+    san::{self, Annotations},
+    uci::{self, UciMove},
+    zobrist, Move,
+};
+use crate::chess::{Board, File, Piece, PieceType, Rank, Square};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Color {
@@ -30,30 +26,1360 @@ impl std::fmt::Display for Color {
     }
 }
 
+impl Color {
+    pub fn opposite(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MoveError {
+    NotAMove,
+    IllegalMove,
+    AmbiguousMove,
+    LeavesKingInCheck,
+    IncorrectAnnotation,
+    MissingPromotion,
+    InvalidPromotion(PieceType),
+    NoDrawAvailable,
+    GameOver,
+    NothingToUndo,
+    NothingToRedo,
+    // The result would have the player who isn't to move resign
+    OpponentCannotResign,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use MoveError::*;
+        match self {
+            NotAMove => write!(f, "Not a move on the board"),
+            IllegalMove => write!(f, "No such piece can make that move"),
+            AmbiguousMove => write!(
+                f,
+                "Ambiguous move, specify the file or rank the piece moves from"
+            ),
+            LeavesKingInCheck => write!(f, "Move would leave the king in check"),
+            IncorrectAnnotation => {
+                write!(f, "Check or checkmate annotation does not match the move")
+            }
+            MissingPromotion => write!(f, "Pawns reaching the last rank must promote, e.g. e8=Q"),
+            InvalidPromotion(piece) => write!(f, "Pawns cannot promote to a {:?}", piece),
+            NoDrawAvailable => write!(f, "There is no draw offer to accept or draw to claim"),
+            GameOver => write!(f, "The game has already ended"),
+            NothingToUndo => write!(f, "There are no moves to take back"),
+            NothingToRedo => write!(f, "There are no taken back moves to play again"),
+            OpponentCannotResign => write!(
+                f,
+                "Only the player to move can resign, with 1-0 for Black or 0-1 for White"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    // The winner of the game
+    Checkmate(Color),
+    Stalemate,
+    Draw(DrawReason),
+    // The player who resigned
+    Resignation(Color),
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use GameStatus::*;
+        match self {
+            Ongoing => write!(f, "The game is still in progress"),
+            Checkmate(winner) => write!(f, "Checkmate, {} wins", winner),
+            Stalemate => write!(f, "Stalemate, the game is drawn"),
+            Draw(reason) => write!(f, "The game is drawn by {}", reason),
+            Resignation(color) => write!(f, "{} resigns, {} wins", color, color.opposite()),
+        }
+    }
+}
+
+impl GameStatus {
+    pub fn get_result(&self) -> Option<GameResult> {
+        match self {
+            GameStatus::Ongoing => None,
+            GameStatus::Checkmate(Color::White) | GameStatus::Resignation(Color::Black) => {
+                Some(GameResult::WhiteWins)
+            }
+            GameStatus::Checkmate(Color::Black) | GameStatus::Resignation(Color::White) => {
+                Some(GameResult::BlackWins)
+            }
+            GameStatus::Stalemate | GameStatus::Draw(_) => Some(GameResult::Draw),
+        }
+    }
+}
+
+// What a move changed that can't be worked out from the move itself,
+// so that it can be taken back exactly
+#[derive(Debug, Copy, Clone)]
+struct Unmake {
+    // The piece that was taken and the square it was on, which differs from the target en passant
+    captured: Option<(Square, Piece)>,
+    castling_rights: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    zobrist_key: u64,
+}
+
+// Something taken back with undo, which redo can put back
+#[derive(Debug, Copy, Clone)]
+enum Undone {
+    Move(MoveType),
+    // A resignation or agreed draw
+    Termination(GameStatus),
+}
+
+#[derive(Clone)]
 pub struct Game {
-    pub board: Board,
-    pub turn: Color,
+    // The position is private so it can't change without the Zobrist key changing with it
+    board: Board,
+    turn: Color,
+    castling_rights: CastlingRights,
+    // The square a pawn skipped over with a double push on the last move
+    en_passant: Option<Square>,
+    // The number of moves by either player since the last capture or pawn move
+    pub halfmove_clock: u32,
+    // Starts at 1 and goes up after each move by Black
+    pub fullmove_number: u32,
+    // The Zobrist key of the position, covering everything the repetition rules compare
+    zobrist_key: u64,
+    // The key of every position reached so far, including the current one
+    position_history: Vec<u64>,
+    // Every move played so far, in order
+    move_history: Vec<MoveType>,
+    // What is needed to take back each move in the move history
+    unmake_history: Vec<Unmake>,
+    // Moves and endings that were taken back, with the most recent last
+    undone: Vec<Undone>,
+    // The position the moves were played from, so the game can be replayed
+    starting_fen: String,
+    // The player with a draw offer waiting for the opponent to accept
+    pub draw_offer: Option<Color>,
+    // How the game ended, if it was ended by a player rather than by the position
+    termination: Option<GameStatus>,
 }
 
 impl Game {
     pub fn new(starting_position: HashMap<Square, Piece>) -> Game {
-        Game {
-            board: Board::new(starting_position),
-            turn: Color::White,
+        let board = Board::new(starting_position);
+        // Assume castling is still possible wherever the king and rook are on their starting squares
+        let mut castling_rights = CastlingRights::none();
+        for color in [Color::White, Color::Black] {
+            for castle_type in [CastleType::Short, CastleType::Long] {
+                let castle_squares = CastleSquares::new(color, castle_type);
+                castling_rights.set(
+                    color,
+                    castle_type,
+                    castle_squares.are_pieces_home(&board, color),
+                );
+            }
+        }
+        Game::from_position(board, Color::White, castling_rights, None, 0, 1)
+    }
+
+    // Set up a game that is already under way, e.g. from a FEN record
+    pub fn from_position(
+        board: Board,
+        turn: Color,
+        castling_rights: CastlingRights,
+        en_passant: Option<Square>,
+        halfmove_clock: u32,
+        fullmove_number: u32,
+    ) -> Game {
+        let mut game = Game {
+            board,
+            turn,
+            castling_rights,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+            zobrist_key: 0,
+            position_history: Vec::new(),
+            move_history: Vec::new(),
+            unmake_history: Vec::new(),
+            undone: Vec::new(),
+            starting_fen: String::new(),
+            draw_offer: None,
+            termination: None,
+        };
+        game.starting_fen = game.to_fen();
+        game.zobrist_key = zobrist::compute(&game);
+        game.record_position();
+        game
+    }
+
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn get_turn(&self) -> Color {
+        self.turn
+    }
+
+    pub fn get_castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
+
+    pub fn get_en_passant(&self) -> Option<Square> {
+        self.en_passant
+    }
+
+    pub fn do_move(&mut self, move_: Move) -> Result<(), MoveError> {
+        if self.status() != GameStatus::Ongoing {
+            return Err(MoveError::GameOver);
+        }
+        match move_ {
+            Move::DrawOffer => self.offer_draw(),
+            Move::EndOfGame(GameResult::Draw) => self.accept_draw(),
+            // The result is written from the winner's side, so the other player is resigning
+            Move::EndOfGame(GameResult::WhiteWins) => self.resign_with_result(Color::Black),
+            Move::EndOfGame(GameResult::BlackWins) => self.resign_with_result(Color::White),
+            _ => {
+                let resolved = san::resolve(self, &move_, Annotations::Lenient)?;
+                self.play_resolved(&resolved);
+                Ok(())
+            }
+        }
+    }
+
+    // Play a move given in coordinate notation, e.g. from an engine
+    pub fn do_uci_move(&mut self, uci_move: &UciMove) -> Result<(), MoveError> {
+        if self.status() != GameStatus::Ongoing {
+            return Err(MoveError::GameOver);
+        }
+        let resolved = uci::resolve(self, uci_move)?;
+        self.play_resolved(&resolved);
+        Ok(())
+    }
+
+    fn play_resolved(&mut self, resolved: &MoveType) {
+        // Playing on instead of accepting declines the opponent's offer
+        if self.draw_offer == Some(self.turn.opposite()) {
+            self.draw_offer = None;
+        }
+        // A new move replaces any that were taken back
+        self.undone.clear();
+        self.make_move(resolved);
+    }
+
+    // Take back the last move, or reopen a game that was resigned or agreed drawn
+    pub fn undo(&mut self) -> Result<(), MoveError> {
+        let undone = match self.termination.take() {
+            Some(termination) => Undone::Termination(termination),
+            None => Undone::Move(self.unmake_move().ok_or(MoveError::NothingToUndo)?),
+        };
+        // Only withdraw a draw offer once something has actually been taken back
+        self.draw_offer = None;
+        self.undone.push(undone);
+        Ok(())
+    }
+
+    // Play the last move that was taken back again, or end the game again
+    pub fn redo(&mut self) -> Result<(), MoveError> {
+        if self.termination.is_some() {
+            return Err(MoveError::GameOver);
+        }
+        match self.undone.pop().ok_or(MoveError::NothingToRedo)? {
+            Undone::Move(move_type) => {
+                self.draw_offer = None;
+                self.make_move(&move_type);
+            }
+            Undone::Termination(termination) => self.termination = Some(termination),
         }
+        Ok(())
     }
 
-    pub fn do_move(&self, move_: Move) {}
+    // Offer a draw, which stays open until the opponent has played their next move.
+    // If the opponent has already offered a draw, this accepts it instead
+    pub fn offer_draw(&mut self) -> Result<(), MoveError> {
+        if self.draw_offer == Some(self.turn.opposite()) {
+            return self.accept_draw();
+        }
+        self.draw_offer = Some(self.turn);
+        Ok(())
+    }
+
+    // Accept the opponent's draw offer, or claim a draw by the fifty-move rule
+    // or threefold repetition
+    pub fn accept_draw(&mut self) -> Result<(), MoveError> {
+        let reason = if self.draw_offer == Some(self.turn.opposite()) {
+            DrawReason::Agreement
+        } else {
+            self.claimable_draw().ok_or(MoveError::NoDrawAvailable)?
+        };
+        self.terminate(GameStatus::Draw(reason));
+        Ok(())
+    }
+
+    pub fn decline_draw(&mut self) -> Result<(), MoveError> {
+        if self.draw_offer != Some(self.turn.opposite()) {
+            return Err(MoveError::NoDrawAvailable);
+        }
+        self.draw_offer = None;
+        Ok(())
+    }
+
+    // Either player may resign at any point, not just on their own turn
+    pub fn resign(&mut self, color: Color) -> Result<(), MoveError> {
+        self.terminate(GameStatus::Resignation(color));
+        Ok(())
+    }
+
+    // Resign by entering the result, which only the player to move may do,
+    // so nobody can type the result that favours themselves to resign for their opponent
+    fn resign_with_result(&mut self, color: Color) -> Result<(), MoveError> {
+        if color != self.turn {
+            return Err(MoveError::OpponentCannotResign);
+        }
+        self.resign(color)
+    }
+
+    // End the game by a player's choice, which like a new move replaces anything taken back
+    fn terminate(&mut self, termination: GameStatus) {
+        self.draw_offer = None;
+        self.undone.clear();
+        self.termination = Some(termination);
+    }
+
+    // Apply a fully resolved move, as produced by move generation
+    pub fn make_move(&mut self, move_type: &MoveType) {
+        let mut unmake = Unmake {
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            zobrist_key: self.zobrist_key,
+        };
+        // Take out everything but the side to move, which just flips, and add it back at the end
+        self.zobrist_key ^= self.board.get_zobrist_key()
+            ^ zobrist::get_castling_key(&self.castling_rights)
+            ^ zobrist::get_en_passant_key(&self.board, self.turn, self.en_passant);
+        let en_passant = self.en_passant.take();
+        unmake.captured = move_pieces(&mut self.board, self.turn, en_passant, move_type);
+        self.halfmove_clock += 1;
+        match *move_type {
+            MoveType::Normal {
+                from: Some(Disambiguation::Square(from)),
+                to,
+                piece,
+            }
+            | MoveType::Capture {
+                from: Some(Disambiguation::Square(from)),
+                to,
+                piece,
+            } => {
+                if piece == PieceType::Pawn || unmake.captured.is_some() {
+                    self.halfmove_clock = 0;
+                }
+                if piece == PieceType::Pawn && from.rank_index().abs_diff(to.rank_index()) == 2 {
+                    let skipped_rank = (from.rank_index() + to.rank_index()) / 2;
+                    self.en_passant = Some(Square::from_index(from.file_index(), skipped_rank));
+                }
+                self.castling_rights.update(&from);
+                self.castling_rights.update(&to);
+            }
+            MoveType::Promotion { .. } => self.halfmove_clock = 0,
+            MoveType::PromotionCapture { to, .. } => {
+                self.halfmove_clock = 0;
+                self.castling_rights.update(&to);
+            }
+            MoveType::Castle(castle_type) => {
+                let castle_squares = CastleSquares::new(self.turn, castle_type);
+                self.castling_rights.update(&castle_squares.king_from);
+            }
+            _ => unreachable!(),
+        }
+        if self.turn == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = self.turn.opposite();
+        self.zobrist_key ^= self.board.get_zobrist_key()
+            ^ zobrist::get_castling_key(&self.castling_rights)
+            ^ zobrist::get_en_passant_key(&self.board, self.turn, self.en_passant)
+            ^ zobrist::get_turn_key(Color::Black);
+        self.check_zobrist_key();
+        self.move_history.push(*move_type);
+        self.unmake_history.push(unmake);
+        self.record_position();
+    }
+
+    // Take back the last move made, putting the position back exactly as it was.
+    // Returns the move, or None if there are no moves to take back
+    pub fn unmake_move(&mut self) -> Option<MoveType> {
+        let move_type = self.move_history.pop()?;
+        let unmake = self
+            .unmake_history
+            .pop()
+            .expect("Every move has a matching unmake");
+        self.position_history.pop();
+        self.turn = self.turn.opposite();
+        if self.turn == Color::Black {
+            self.fullmove_number -= 1;
+        }
+        match move_type {
+            MoveType::Normal {
+                from: Some(Disambiguation::Square(from)),
+                to,
+                ..
+            }
+            | MoveType::Capture {
+                from: Some(Disambiguation::Square(from)),
+                to,
+                ..
+            } => {
+                self.board.move_piece(&to, &from);
+            }
+            MoveType::Promotion { to, .. } => {
+                let from = self.get_promotion_origin(to.file(), &to);
+                self.board.set(&to, None);
+                self.board.set(&from, Some(self.get_piece(PieceType::Pawn)));
+            }
+            MoveType::PromotionCapture { from, to, .. } => {
+                let from = self.get_promotion_origin(from, &to);
+                self.board.set(&to, None);
+                self.board.set(&from, Some(self.get_piece(PieceType::Pawn)));
+            }
+            MoveType::Castle(castle_type) => {
+                let castle_squares = CastleSquares::new(self.turn, castle_type);
+                self.board
+                    .move_piece(&castle_squares.king_to, &castle_squares.king_from);
+                self.board
+                    .move_piece(&castle_squares.rook_to, &castle_squares.rook_from);
+            }
+            _ => panic!("Tried to unmake a move that has not been resolved"),
+        }
+        if let Some((square, piece)) = unmake.captured {
+            self.board.set(&square, Some(piece));
+        }
+        self.castling_rights = unmake.castling_rights;
+        self.en_passant = unmake.en_passant;
+        self.halfmove_clock = unmake.halfmove_clock;
+        self.zobrist_key = unmake.zobrist_key;
+        self.check_zobrist_key();
+        Some(move_type)
+    }
+
+    pub fn get_zobrist_key(&self) -> u64 {
+        self.zobrist_key
+    }
+
+    // Make sure the incrementally updated key matches working it out from scratch,
+    // which is too slow to do outside debug builds
+    fn check_zobrist_key(&self) {
+        debug_assert!(
+            self.zobrist_key == zobrist::compute(self),
+            "Zobrist key is out of date for {}",
+            self.to_fen()
+        );
+    }
+
+    pub fn get_move_history(&self) -> &[MoveType] {
+        &self.move_history
+    }
+
+    pub fn get_starting_fen(&self) -> &str {
+        &self.starting_fen
+    }
+
+    fn record_position(&mut self) {
+        self.position_history.push(self.zobrist_key);
+    }
+
+    // Count how many times the current position has occurred
+    fn get_repetitions(&self) -> usize {
+        let current = self.position_history.last();
+        self.position_history
+            .iter()
+            .filter(|position| Some(*position) == current)
+            .count()
+    }
+
+    // Get a draw the current player could claim, but which does not end the game by itself
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.get_repetitions() >= 3 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.halfmove_clock >= 100 {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    // Get a draw that ends the game immediately, without needing to be claimed
+    fn automatic_draw(&self) -> Option<DrawReason> {
+        if self.get_repetitions() >= 5 {
+            Some(DrawReason::FivefoldRepetition)
+        } else if self.halfmove_clock >= 150 {
+            Some(DrawReason::SeventyFiveMoveRule)
+        } else if draws::has_insufficient_material(&self.board) {
+            Some(DrawReason::InsufficientMaterial)
+        } else {
+            None
+        }
+    }
+
+    // Get a piece belonging to the current player
+    pub fn get_piece(&self, piece_type: PieceType) -> Piece {
+        Piece {
+            piece_type,
+            color: self.turn,
+        }
+    }
+
+    // Get the square of the pawn that is promoting on a square from the given file
+    pub fn get_promotion_origin(&self, from: File, to: &Square) -> Square {
+        get_promotion_origin(self.turn, from, to)
+    }
+
+    pub fn is_in_check(&self, color: Color) -> bool {
+        self.board.is_in_check(color)
+    }
+
+    // Check that a generated move does not leave the mover's own king attacked,
+    // which covers pins, discovered checks and the king walking into check.
+    // Only the pieces matter for this, so the move is tried on a copy of the board alone
+    pub fn is_legal(&self, move_type: &MoveType) -> bool {
+        let mut board = self.board.clone();
+        move_pieces(&mut board, self.turn, self.en_passant, move_type);
+        !board.is_in_check(self.turn)
+    }
+
+    // Checkmate takes priority over the automatic draw rules
+    pub fn status(&self) -> GameStatus {
+        if let Some(termination) = self.termination {
+            termination
+        } else if !self.legal_moves().is_empty() {
+            match self.automatic_draw() {
+                Some(reason) => GameStatus::Draw(reason),
+                None => GameStatus::Ongoing,
+            }
+        } else if self.is_in_check(self.turn) {
+            GameStatus::Checkmate(self.turn.opposite())
+        } else {
+            GameStatus::Stalemate
+        }
+    }
+
+    // Get every move the current player can actually play
+    pub fn legal_moves(&self) -> Vec<MoveType> {
+        self.get_all_possible_moves()
+            .into_iter()
+            .filter(|move_type| self.is_legal(move_type))
+            .collect()
+    }
 
     // Get all possible moves for every piece for the current player,
     // ignoring the state of the board after the move
-    // pub fn get_all_possible_moves(&self) -> Vec<Move> {
-    //     let mut moves = Vec::<Move>::new();
-    //     for (square, piece) in self.board.get_all_pieces() {
-    //         if piece.color == self.turn {
-    //             moves.append(&mut piece.get_moves(&self.board, &square));
-    //         }
-    //     }
-    //     moves
-    // }
+    pub fn get_all_possible_moves(&self) -> Vec<MoveType> {
+        let mut moves = Vec::<MoveType>::new();
+        for (square, piece) in self.board.get_all_pieces() {
+            if piece.color == self.turn {
+                moves.append(&mut piece.get_moves(&self.board, &square, self.en_passant));
+            }
+        }
+        moves.append(&mut self.get_castling_moves());
+        moves
+    }
+
+    // Castling needs the rights still in place, the king and rook on their starting squares,
+    // nothing between them, and no attacks on the king as it starts, passes through or lands
+    fn get_castling_moves(&self) -> Vec<MoveType> {
+        [CastleType::Short, CastleType::Long]
+            .into_iter()
+            .filter(|castle_type| {
+                let castle_squares = CastleSquares::new(self.turn, *castle_type);
+                self.castling_rights.get(self.turn, *castle_type)
+                    && castle_squares.are_pieces_home(&self.board, self.turn)
+                    && castle_squares
+                        .get_empty_squares()
+                        .iter()
+                        .all(|square| self.board.get(square).is_none())
+                    && castle_squares
+                        .get_king_path()
+                        .iter()
+                        .all(|square| !self.board.is_square_attacked(square, self.turn.opposite()))
+            })
+            .map(MoveType::Castle)
+            .collect()
+    }
+}
+
+// Move the pieces on the board for a resolved move by the given player,
+// returning the piece that was taken and the square it was taken on
+fn move_pieces(
+    board: &mut Board,
+    turn: Color,
+    en_passant: Option<Square>,
+    move_type: &MoveType,
+) -> Option<(Square, Piece)> {
+    let promote = |piece_type| Piece {
+        piece_type,
+        color: turn,
+    };
+    match *move_type {
+        MoveType::Normal {
+            from: Some(Disambiguation::Square(from)),
+            to,
+            piece,
+        }
+        | MoveType::Capture {
+            from: Some(Disambiguation::Square(from)),
+            to,
+            piece,
+        } => {
+            let mut captured = board.get(&to).map(|captured| (to, *captured));
+            if piece == PieceType::Pawn && en_passant == Some(to) {
+                // A pawn taken en passant is beside the moving pawn, not on the target square
+                let taken = Square::from_index(to.file_index(), from.rank_index());
+                captured = board.set(&taken, None).map(|pawn| (taken, pawn));
+            }
+            board.move_piece(&from, &to);
+            captured
+        }
+        MoveType::Promotion { to, promote_to } => {
+            board.set(&get_promotion_origin(turn, to.file(), &to), None);
+            board.set(&to, Some(promote(promote_to)));
+            None
+        }
+        MoveType::PromotionCapture {
+            from,
+            to,
+            promote_to,
+        } => {
+            board.set(&get_promotion_origin(turn, from, &to), None);
+            board
+                .set(&to, Some(promote(promote_to)))
+                .map(|captured| (to, captured))
+        }
+        MoveType::Castle(castle_type) => {
+            let castle_squares = CastleSquares::new(turn, castle_type);
+            board.move_piece(&castle_squares.king_from, &castle_squares.king_to);
+            board.move_piece(&castle_squares.rook_from, &castle_squares.rook_to);
+            None
+        }
+        _ => panic!("Tried to make a move that has not been resolved"),
+    }
+}
+
+fn get_promotion_origin(turn: Color, from: File, to: &Square) -> Square {
+    let rank_index = match turn {
+        Color::White => to.rank_index() - 1,
+        Color::Black => to.rank_index() + 1,
+    };
+    Square::new(from, Rank::from_index(rank_index).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{generate_starting_position, piece, square},
+        *,
+    };
+
+    fn play(game: &mut Game, input: &str) -> Result<(), MoveError> {
+        game.do_move(Move::parse(input).unwrap())
+    }
+
+    #[test]
+    fn pawn_push() {
+        let mut game = Game::new(generate_starting_position());
+        assert!(play(&mut game, "e3") == Ok(()));
+        assert!(game.board.get(&square!(E 2)).is_none());
+        assert!(game.board.get(&square!(E 3)).is_some());
+        assert!(game.turn == Color::Black);
+    }
+
+    #[test]
+    fn pawn_capture() {
+        let mut game = Game::new(HashMap::from([
+            (square!(E 4), piece!(White, Pawn)),
+            (square!(D 5), piece!(Black, Pawn)),
+        ]));
+        assert!(play(&mut game, "exd5") == Ok(()));
+        let taken = game.board.get(&square!(D 5)).unwrap();
+        assert!(taken.color == Color::White);
+        assert!(game.board.get_all_pieces().len() == 1);
+    }
+
+    #[test]
+    fn knight_move() {
+        let mut game = Game::new(generate_starting_position());
+        assert!(play(&mut game, "Nf3") == Ok(()));
+        let knight = game.board.get(&square!(F 3)).unwrap();
+        assert!(knight.piece_type == PieceType::Knight);
+        assert!(game.board.get(&square!(G 1)).is_none());
+    }
+
+    #[test]
+    fn starting_moves() {
+        let game = Game::new(generate_starting_position());
+        assert!(game.legal_moves().len() == 20);
+    }
+
+    #[test]
+    fn double_push_sets_en_passant() {
+        let mut game = Game::new(generate_starting_position());
+        assert!(play(&mut game, "e4") == Ok(()));
+        assert!(game.en_passant == Some(square!(E 3)));
+        assert!(play(&mut game, "Nf6") == Ok(()));
+        assert!(game.en_passant.is_none());
+    }
+
+    #[test]
+    fn opening_en_passant() {
+        let mut game = Game::new(generate_starting_position());
+        for input in ["e4", "a6", "e5", "d5", "exd6"] {
+            assert!(play(&mut game, input) == Ok(()));
+        }
+        assert!(game.board.get(&square!(D 5)).is_none());
+        assert!(game.board.get_all_pieces().len() == 31);
+    }
+
+    #[test]
+    fn illegal_move() {
+        let mut game = Game::new(generate_starting_position());
+        assert!(play(&mut game, "e5") == Err(MoveError::IllegalMove));
+        assert!(game.turn == Color::White);
+    }
+
+    #[test]
+    fn pinned_piece() {
+        let mut game = Game::new(HashMap::from([
+            (square!(E 1), piece!(White, King)),
+            (square!(E 2), piece!(White, Knight)),
+            (square!(E 8), piece!(Black, Rook)),
+        ]));
+        assert!(play(&mut game, "Nc3") == Err(MoveError::LeavesKingInCheck));
+        assert!(game.legal_moves().len() == 4);
+    }
+
+    #[test]
+    fn king_cannot_walk_into_check() {
+        let mut game = Game::new(HashMap::from([
+            (square!(E 1), piece!(White, King)),
+            (square!(D 8), piece!(Black, Rook)),
+            (square!(F 3), piece!(Black, Pawn)),
+        ]));
+        assert!(play(&mut game, "Kd2") == Err(MoveError::LeavesKingInCheck));
+        assert!(play(&mut game, "Ke2") == Err(MoveError::LeavesKingInCheck));
+        assert!(play(&mut game, "Kf2") == Ok(()));
+    }
+
+    #[test]
+    fn discovered_check() {
+        let mut game = Game::new(HashMap::from([
+            (square!(E 1), piece!(White, Rook)),
+            (square!(E 4), piece!(White, Bishop)),
+            (square!(A 1), piece!(White, King)),
+            (square!(E 8), piece!(Black, King)),
+        ]));
+        assert!(!game.is_in_check(Color::Black));
+        assert!(play(&mut game, "Bd5") == Ok(()));
+        assert!(game.is_in_check(Color::Black));
+        assert!(!game.is_in_check(Color::White));
+    }
+
+    #[test]
+    fn must_escape_check() {
+        let mut game = Game::new(HashMap::from([
+            (square!(E 1), piece!(White, King)),
+            (square!(A 2), piece!(White, Pawn)),
+            (square!(E 8), piece!(Black, Queen)),
+        ]));
+        assert!(game.is_in_check(Color::White));
+        assert!(play(&mut game, "a3") == Err(MoveError::LeavesKingInCheck));
+        assert!(game.legal_moves().iter().all(|move_type| matches!(
+            move_type,
+            MoveType::Normal {
+                piece: PieceType::King,
+                ..
+            }
+        )));
+    }
+
+    fn castling_position() -> HashMap<Square, Piece> {
+        HashMap::from([
+            (square!(E 1), piece!(White, King)),
+            (square!(A 1), piece!(White, Rook)),
+            (square!(H 1), piece!(White, Rook)),
+            (square!(E 8), piece!(Black, King)),
+            (square!(A 8), piece!(Black, Rook)),
+            (square!(H 8), piece!(Black, Rook)),
+        ])
+    }
+
+    #[test]
+    fn short_castle() {
+        let mut game = Game::new(castling_position());
+        assert!(play(&mut game, "O-O") == Ok(()));
+        assert!(game.board.get(&square!(G 1)).unwrap().piece_type == PieceType::King);
+        assert!(game.board.get(&square!(F 1)).unwrap().piece_type == PieceType::Rook);
+        assert!(game.board.get(&square!(H 1)).is_none());
+        assert!(!game.castling_rights.white_short && !game.castling_rights.white_long);
+        assert!(game.castling_rights.black_short && game.castling_rights.black_long);
+    }
+
+    #[test]
+    fn long_castle() {
+        let mut game = Game::new(castling_position());
+        play(&mut game, "Kd1").unwrap();
+        assert!(play(&mut game, "O-O-O") == Ok(()));
+        assert!(game.board.get(&square!(C 8)).unwrap().piece_type == PieceType::King);
+        assert!(game.board.get(&square!(D 8)).unwrap().piece_type == PieceType::Rook);
+    }
+
+    #[test]
+    fn rook_move_loses_rights() {
+        let mut game = Game::new(castling_position());
+        play(&mut game, "Rb1").unwrap();
+        assert!(game.castling_rights.white_short && !game.castling_rights.white_long);
+        play(&mut game, "Rh7").unwrap();
+        assert!(!game.castling_rights.black_short && game.castling_rights.black_long);
+        play(&mut game, "Ra1").unwrap();
+        play(&mut game, "Rh8").unwrap();
+        assert!(play(&mut game, "O-O-O") == Err(MoveError::IllegalMove));
+    }
+
+    #[test]
+    fn rook_capture_loses_rights() {
+        let mut game = Game::new(castling_position());
+        play(&mut game, "Rxa8").unwrap();
+        assert!(!game.castling_rights.black_long && game.castling_rights.black_short);
+    }
+
+    #[test]
+    fn cannot_castle_through_check() {
+        let mut position = castling_position();
+        position.insert(square!(F 8), piece!(Black, Rook));
+        position.remove(&square!(H 8));
+        let mut game = Game::new(position);
+        assert!(play(&mut game, "O-O") == Err(MoveError::IllegalMove));
+        assert!(play(&mut game, "O-O-O") == Ok(()));
+    }
+
+    #[test]
+    fn cannot_castle_out_of_check() {
+        let mut position = castling_position();
+        position.insert(square!(E 5), piece!(Black, Rook));
+        let mut game = Game::new(position);
+        assert!(play(&mut game, "O-O") == Err(MoveError::IllegalMove));
+        assert!(play(&mut game, "O-O-O") == Err(MoveError::IllegalMove));
+    }
+
+    #[test]
+    fn cannot_castle_without_king_and_rook_home() {
+        // The rights alone are not enough if the pieces are missing or elsewhere
+        for mut pieces in [
+            HashMap::from([
+                (square!(E 1), piece!(White, King)),
+                (square!(H 1), piece!(White, Knight)),
+            ]),
+            HashMap::from([(square!(E 1), piece!(White, King))]),
+            HashMap::from([
+                (square!(D 1), piece!(White, King)),
+                (square!(H 1), piece!(White, Rook)),
+            ]),
+        ] {
+            // A black king and pawn keep the game from being drawn on material alone
+            pieces.insert(square!(E 8), piece!(Black, King));
+            pieces.insert(square!(A 7), piece!(Black, Pawn));
+            let mut rights = CastlingRights::none();
+            rights.white_short = true;
+            let mut game =
+                Game::from_position(Board::new(pieces), Color::White, rights, None, 0, 1);
+            assert!(play(&mut game, "O-O") == Err(MoveError::IllegalMove));
+        }
+    }
+
+    #[test]
+    fn cannot_castle_through_pieces() {
+        let mut game = Game::new(generate_starting_position());
+        assert!(play(&mut game, "O-O") == Err(MoveError::IllegalMove));
+    }
+
+    // Set up a position where white can take a pawn that has just moved to d5
+    fn en_passant_position(pieces: HashMap<Square, Piece>) -> Game {
+        let en_passant = Some(square!(D 6));
+        Game::from_position(
+            Board::new(pieces),
+            Color::White,
+            CastlingRights::none(),
+            en_passant,
+            0,
+            1,
+        )
+    }
+
+    #[test]
+    fn en_passant_capture() {
+        // Simulate black having just played d7-d5
+        let mut game = en_passant_position(HashMap::from([
+            (square!(E 5), piece!(White, Pawn)),
+            (square!(D 5), piece!(Black, Pawn)),
+        ]));
+        assert!(play(&mut game, "exd6") == Ok(()));
+        assert!(game.board.get(&square!(D 5)).is_none());
+        assert!(game.board.get(&square!(D 6)).unwrap().color == Color::White);
+        assert!(game.board.get_all_pieces().len() == 1);
+        assert!(game.en_passant.is_none());
+    }
+
+    #[test]
+    fn en_passant_expires() {
+        let mut game = en_passant_position(HashMap::from([
+            (square!(E 5), piece!(White, Pawn)),
+            (square!(D 5), piece!(Black, Pawn)),
+            (square!(H 2), piece!(White, Pawn)),
+            (square!(H 7), piece!(Black, Pawn)),
+        ]));
+        play(&mut game, "h3").unwrap();
+        play(&mut game, "h6").unwrap();
+        assert!(play(&mut game, "exd6") == Err(MoveError::IllegalMove));
+    }
+
+    #[test]
+    fn en_passant_discovered_check() {
+        // Taking en passant would remove both pawns from the rank, exposing the king
+        let mut game = en_passant_position(HashMap::from([
+            (square!(A 5), piece!(White, King)),
+            (square!(E 5), piece!(White, Pawn)),
+            (square!(D 5), piece!(Black, Pawn)),
+            (square!(H 5), piece!(Black, Rook)),
+        ]));
+        assert!(play(&mut game, "exd6") == Err(MoveError::LeavesKingInCheck));
+    }
+
+    fn promotion_position() -> HashMap<Square, Piece> {
+        HashMap::from([
+            (square!(A 1), piece!(White, King)),
+            (square!(E 7), piece!(White, Pawn)),
+            (square!(D 8), piece!(Black, Rook)),
+            (square!(H 8), piece!(Black, King)),
+        ])
+    }
+
+    #[test]
+    fn promotion() {
+        let mut game = Game::new(promotion_position());
+        assert!(play(&mut game, "e8=N") == Ok(()));
+        assert!(game.board.get(&square!(E 8)).unwrap().piece_type == PieceType::Knight);
+        assert!(game.board.get(&square!(E 7)).is_none());
+    }
+
+    #[test]
+    fn promotion_capture() {
+        let mut game = Game::new(promotion_position());
+        assert!(play(&mut game, "exd8=Q+") == Ok(()));
+        let queen = game.board.get(&square!(D 8)).unwrap();
+        assert!(queen.piece_type == PieceType::Queen && queen.color == Color::White);
+        assert!(game.board.get_all_pieces().len() == 3);
+    }
+
+    #[test]
+    fn black_promotion() {
+        let mut game = Game::from_fen("K7/8/8/8/8/8/1p6/7k b - - 0 1").unwrap();
+        assert!(play(&mut game, "b1=R") == Ok(()));
+        assert!(game.board.get(&square!(B 1)).unwrap().piece_type == PieceType::Rook);
+    }
+
+    #[test]
+    fn promotion_required() {
+        let mut game = Game::new(promotion_position());
+        assert!(play(&mut game, "e8") == Err(MoveError::MissingPromotion));
+        assert!(play(&mut game, "exd8") == Err(MoveError::MissingPromotion));
+    }
+
+    #[test]
+    fn last_rank_without_pawn() {
+        let mut game = Game::from_fen("4k3/p7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(play(&mut game, "e8") == Err(MoveError::IllegalMove));
+        let mut game = Game::new(promotion_position());
+        assert!(play(&mut game, "exf8") == Err(MoveError::IllegalMove));
+    }
+
+    #[test]
+    fn invalid_promotion() {
+        let mut game = Game::new(promotion_position());
+        assert!(play(&mut game, "e8=K") == Err(MoveError::InvalidPromotion(PieceType::King)));
+        let to_pawn = Move::NoCheck(MoveType::Promotion {
+            to: square!(E 8),
+            promote_to: PieceType::Pawn,
+        });
+        assert!(game.do_move(to_pawn) == Err(MoveError::InvalidPromotion(PieceType::Pawn)));
+    }
+
+    #[test]
+    fn ongoing() {
+        let game = Game::new(generate_starting_position());
+        assert!(game.status() == GameStatus::Ongoing);
+        assert!(game.status().get_result().is_none());
+    }
+
+    #[test]
+    fn checkmate() {
+        let mut game = Game::new(generate_starting_position());
+        for input in ["f3", "e5", "g4", "Qh4#"] {
+            play(&mut game, input).unwrap();
+        }
+        assert!(game.status() == GameStatus::Checkmate(Color::Black));
+        assert!(game.status().get_result() == Some(GameResult::BlackWins));
+        assert!(play(&mut game, "Kf2") == Err(MoveError::GameOver));
+    }
+
+    #[test]
+    fn stalemate() {
+        let mut game = Game::new(HashMap::from([
+            (square!(A 8), piece!(Black, King)),
+            (square!(B 6), piece!(White, King)),
+            (square!(C 1), piece!(White, Queen)),
+        ]));
+        play(&mut game, "Qc7").unwrap();
+        assert!(game.status() == GameStatus::Stalemate);
+        assert!(game.status().get_result() == Some(GameResult::Draw));
+        assert!(play(&mut game, "Ka7") == Err(MoveError::GameOver));
+    }
+
+    fn shuffle_knights(game: &mut Game, times: usize) {
+        for _ in 0..times {
+            for input in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+                play(game, input).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut game = Game::new(generate_starting_position());
+        shuffle_knights(&mut game, 1);
+        assert!(game.claimable_draw().is_none());
+        shuffle_knights(&mut game, 1);
+        assert!(game.claimable_draw() == Some(DrawReason::ThreefoldRepetition));
+        assert!(game.status() == GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn fivefold_repetition() {
+        let mut game = Game::new(generate_starting_position());
+        shuffle_knights(&mut game, 3);
+        assert!(game.status() == GameStatus::Ongoing);
+        shuffle_knights(&mut game, 1);
+        assert!(game.status() == GameStatus::Draw(DrawReason::FivefoldRepetition));
+        assert!(play(&mut game, "e4") == Err(MoveError::GameOver));
+        assert!(game.status() == GameStatus::Draw(DrawReason::FivefoldRepetition));
+    }
+
+    #[test]
+    fn repetition_needs_same_castling_rights() {
+        let mut game = Game::new(castling_position());
+        for input in ["Kf1", "Kf8", "Ke1", "Ke8"] {
+            play(&mut game, input).unwrap();
+        }
+        shuffle_kings(&mut game);
+        // The starting position had castling rights, so it only counts towards the later repeats
+        assert!(game.claimable_draw().is_none());
+        shuffle_kings(&mut game);
+        assert!(game.claimable_draw() == Some(DrawReason::ThreefoldRepetition));
+    }
+
+    fn shuffle_kings(game: &mut Game) {
+        for input in ["Kf1", "Kf8", "Ke1", "Ke8"] {
+            play(game, input).unwrap();
+        }
+    }
+
+    #[test]
+    fn fifty_move_rule() {
+        let mut game = Game::new(castling_position());
+        game.halfmove_clock = 99;
+        assert!(game.claimable_draw().is_none());
+        play(&mut game, "Rb1").unwrap();
+        assert!(game.claimable_draw() == Some(DrawReason::FiftyMoveRule));
+        play(&mut game, "Rxh1").unwrap();
+        assert!(game.halfmove_clock == 0);
+        assert!(game.claimable_draw().is_none());
+    }
+
+    #[test]
+    fn seventy_five_move_rule() {
+        let mut game = Game::new(castling_position());
+        game.halfmove_clock = 149;
+        assert!(game.status() == GameStatus::Ongoing);
+        play(&mut game, "Rb1").unwrap();
+        assert!(game.status() == GameStatus::Draw(DrawReason::SeventyFiveMoveRule));
+        // A pawn move would reset the clock, but the game is already over
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 150 100").unwrap();
+        assert!(play(&mut game, "e4") == Err(MoveError::GameOver));
+        assert!(game.status() == GameStatus::Draw(DrawReason::SeventyFiveMoveRule));
+    }
+
+    #[test]
+    fn pawn_move_resets_clock() {
+        let mut game = Game::new(generate_starting_position());
+        play(&mut game, "Nf3").unwrap();
+        assert!(game.halfmove_clock == 1);
+        play(&mut game, "e5").unwrap();
+        assert!(game.halfmove_clock == 0);
+    }
+
+    fn with_kings(pieces: &[(Square, Piece)]) -> Game {
+        let mut position = HashMap::from([
+            (square!(E 1), piece!(White, King)),
+            (square!(E 8), piece!(Black, King)),
+        ]);
+        position.extend(pieces.iter().copied());
+        Game::new(position)
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let draw = GameStatus::Draw(DrawReason::InsufficientMaterial);
+        assert!(with_kings(&[]).status() == draw);
+        assert!(with_kings(&[(square!(C 3), piece!(White, Knight))]).status() == draw);
+        assert!(with_kings(&[(square!(C 3), piece!(Black, Bishop))]).status() == draw);
+        let same_colour_bishops = [
+            (square!(C 1), piece!(White, Bishop)),
+            (square!(F 8), piece!(Black, Bishop)),
+        ];
+        assert!(with_kings(&same_colour_bishops).status() == draw);
+    }
+
+    #[test]
+    fn no_moves_after_insufficient_material() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/4KB2 w - - 0 1").unwrap();
+        let draw = GameStatus::Draw(DrawReason::InsufficientMaterial);
+        assert!(play(&mut game, "Bc4") == Err(MoveError::GameOver));
+        let uci_move = UciMove::parse("f1c4").unwrap();
+        assert!(game.do_uci_move(&uci_move) == Err(MoveError::GameOver));
+        assert!(game.status() == draw);
+        assert!(game.get_move_history().is_empty());
+    }
+
+    #[test]
+    fn sufficient_material() {
+        let ongoing = GameStatus::Ongoing;
+        assert!(with_kings(&[(square!(A 2), piece!(White, Pawn))]).status() == ongoing);
+        assert!(with_kings(&[(square!(A 1), piece!(Black, Rook))]).status() == ongoing);
+        let two_knights = [
+            (square!(C 3), piece!(White, Knight)),
+            (square!(D 3), piece!(White, Knight)),
+        ];
+        assert!(with_kings(&two_knights).status() == ongoing);
+        let opposite_colour_bishops = [
+            (square!(C 1), piece!(White, Bishop)),
+            (square!(C 8), piece!(Black, Bishop)),
+        ];
+        assert!(with_kings(&opposite_colour_bishops).status() == ongoing);
+    }
+
+    #[test]
+    fn draw_offer_accepted() {
+        let mut game = Game::new(generate_starting_position());
+        play(&mut game, "(=)").unwrap();
+        assert!(game.draw_offer == Some(Color::White));
+        assert!(game.turn == Color::White);
+        play(&mut game, "e4").unwrap();
+        assert!(game.draw_offer == Some(Color::White));
+        play(&mut game, "1/2-1/2").unwrap();
+        assert!(game.status() == GameStatus::Draw(DrawReason::Agreement));
+        assert!(play(&mut game, "e5") == Err(MoveError::GameOver));
+    }
+
+    #[test]
+    fn draw_offer_declined_by_moving() {
+        let mut game = Game::new(generate_starting_position());
+        play(&mut game, "(=)").unwrap();
+        play(&mut game, "e4").unwrap();
+        play(&mut game, "e5").unwrap();
+        assert!(game.draw_offer.is_none());
+        play(&mut game, "Nf3").unwrap();
+        assert!(play(&mut game, "1/2-1/2") == Err(MoveError::NoDrawAvailable));
+        assert!(game.status() == GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn draw_offer_declined() {
+        let mut game = Game::new(generate_starting_position());
+        play(&mut game, "(=)").unwrap();
+        assert!(game.decline_draw() == Err(MoveError::NoDrawAvailable));
+        play(&mut game, "e4").unwrap();
+        assert!(game.decline_draw() == Ok(()));
+        assert!(game.draw_offer.is_none());
+    }
+
+    #[test]
+    fn cannot_accept_own_offer() {
+        let mut game = Game::new(generate_starting_position());
+        play(&mut game, "(=)").unwrap();
+        assert!(play(&mut game, "1/2-1/2") == Err(MoveError::NoDrawAvailable));
+    }
+
+    #[test]
+    fn claim_draw() {
+        let mut game = Game::new(generate_starting_position());
+        shuffle_knights(&mut game, 2);
+        play(&mut game, "1/2-1/2").unwrap();
+        assert!(game.status() == GameStatus::Draw(DrawReason::ThreefoldRepetition));
+    }
+
+    #[test]
+    fn resignation() {
+        let mut game = Game::new(generate_starting_position());
+        play(&mut game, "0-1").unwrap();
+        assert!(game.status() == GameStatus::Resignation(Color::White));
+        assert!(game.status().get_result() == Some(GameResult::BlackWins));
+        assert!(play(&mut game, "e4") == Err(MoveError::GameOver));
+    }
+
+    #[test]
+    fn cannot_resign_for_opponent() {
+        let mut game = Game::new(generate_starting_position());
+        assert!(play(&mut game, "1-0") == Err(MoveError::OpponentCannotResign));
+        assert!(game.status() == GameStatus::Ongoing);
+        play(&mut game, "e4").unwrap();
+        assert!(play(&mut game, "0-1") == Err(MoveError::OpponentCannotResign));
+        assert!(play(&mut game, "1-0") == Ok(()));
+        assert!(game.status() == GameStatus::Resignation(Color::Black));
+    }
+
+    #[test]
+    fn capture_requires_piece() {
+        let mut game = Game::new(generate_starting_position());
+        assert!(play(&mut game, "exd3") == Err(MoveError::IllegalMove));
+    }
+
+    // Make and take back every legal move, checking nothing about the game changes
+    fn check_unmake_all(game: &mut Game) {
+        let fen = game.to_fen();
+        let board = game.board.clone();
+        let history_length = game.position_history.len();
+        for move_type in game.legal_moves() {
+            game.make_move(&move_type);
+            assert!(game.unmake_move() == Some(move_type));
+            assert!(game.to_fen() == fen);
+            assert!(game.board == board);
+            assert!(game.position_history.len() == history_length);
+        }
+    }
+
+    #[test]
+    fn unmake_every_move() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq a3 0 1",
+            "1r2k3/P7/8/8/8/8/5p2/4KN2 b - - 7 40",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+        for fen in fens {
+            check_unmake_all(&mut Game::from_fen(fen).unwrap());
+        }
+    }
+
+    #[test]
+    fn unmake_whole_game() {
+        let mut game = Game::new(generate_starting_position());
+        let mut fens = vec![game.to_fen()];
+        let moves = [
+            "e4", "d5", "exd5", "c5", "dxc6", "Qd6", "cxb7", "Qe6+", "Be2", "Qxe2+", "Nxe2", "Nf6",
+            "bxa8=Q", "Bd7", "O-O", "e5",
+        ];
+        for input in moves {
+            play(&mut game, input).unwrap();
+            fens.push(game.to_fen());
+        }
+        fens.pop();
+        while let Some(fen) = fens.pop() {
+            assert!(game.unmake_move().is_some());
+            assert!(game.to_fen() == fen);
+        }
+        assert!(game.unmake_move().is_none());
+        assert!(game.get_move_history().is_empty());
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut game = Game::new(generate_starting_position());
+        assert!(game.undo() == Err(MoveError::NothingToUndo));
+        play(&mut game, "e4").unwrap();
+        play(&mut game, "e5").unwrap();
+        let fen = game.to_fen();
+        game.undo().unwrap();
+        game.undo().unwrap();
+        assert!(game.to_fen() == Game::new(generate_starting_position()).to_fen());
+        game.redo().unwrap();
+        game.redo().unwrap();
+        assert!(game.to_fen() == fen);
+        assert!(game.redo() == Err(MoveError::NothingToRedo));
+    }
+
+    #[test]
+    fn new_move_clears_redo() {
+        let mut game = Game::new(generate_starting_position());
+        play(&mut game, "e4").unwrap();
+        game.undo().unwrap();
+        play(&mut game, "d4").unwrap();
+        assert!(game.redo() == Err(MoveError::NothingToRedo));
+        assert!(game.get_move_history().len() == 1);
+    }
+
+    #[test]
+    fn undo_resignation() {
+        let mut game = Game::new(generate_starting_position());
+        play(&mut game, "e4").unwrap();
+        play(&mut game, "1-0").unwrap();
+        assert!(game.redo() == Err(MoveError::GameOver));
+        // Only the resignation is taken back, not the move before it
+        game.undo().unwrap();
+        assert!(game.status() == GameStatus::Ongoing);
+        assert!(game.turn == Color::Black);
+        assert!(game.get_move_history().len() == 1);
+        game.redo().unwrap();
+        assert!(game.status() == GameStatus::Resignation(Color::Black));
+        game.undo().unwrap();
+        game.undo().unwrap();
+        game.redo().unwrap();
+        game.redo().unwrap();
+        assert!(game.status() == GameStatus::Resignation(Color::Black));
+    }
+
+    #[test]
+    fn failed_undo_keeps_draw_offer() {
+        let mut game = Game::new(generate_starting_position());
+        play(&mut game, "(=)").unwrap();
+        assert!(game.undo() == Err(MoveError::NothingToUndo));
+        assert!(game.draw_offer == Some(Color::White));
+        play(&mut game, "e4").unwrap();
+        play(&mut game, "(=)").unwrap();
+        game.undo().unwrap();
+        assert!(game.draw_offer.is_none());
+    }
+
+    #[test]
+    fn undo_resignation_before_any_move() {
+        let mut game = Game::new(generate_starting_position());
+        play(&mut game, "0-1").unwrap();
+        assert!(game.undo() == Ok(()));
+        assert!(game.status() == GameStatus::Ongoing);
+        assert!(game.undo() == Err(MoveError::NothingToUndo));
+    }
+
+    #[test]
+    fn undo_keeps_repetitions() {
+        let mut game = Game::new(generate_starting_position());
+        shuffle_knights(&mut game, 2);
+        assert!(game.claimable_draw() == Some(DrawReason::ThreefoldRepetition));
+        game.undo().unwrap();
+        assert!(game.claimable_draw().is_none());
+        game.redo().unwrap();
+        assert!(game.claimable_draw() == Some(DrawReason::ThreefoldRepetition));
+    }
 }
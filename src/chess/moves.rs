@@ -2,20 +2,42 @@ use std::fmt;
 
 use super::{File, PieceType, Rank, Square};
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum CastleType {
     Short,
     Long,
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Disambiguation {
     File(File),
     Rank(Rank),
     Square(Square),
 }
 
-#[derive(PartialEq, Eq)]
+impl Disambiguation {
+    // Check if a fully known origin square fits this (possibly partial) disambiguation
+    pub fn matches(&self, square: &Square) -> bool {
+        match self {
+            Disambiguation::File(file) => square.file() == *file,
+            Disambiguation::Rank(rank) => square.rank() == *rank,
+            Disambiguation::Square(from) => square == from,
+        }
+    }
+}
+
+// Written the same way as in SAN, e.g. "b" for the file in Nbd7
+impl fmt::Display for Disambiguation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Disambiguation::File(file) => write!(f, "{}", file.to_char()),
+            Disambiguation::Rank(rank) => write!(f, "{}", rank),
+            Disambiguation::Square(square) => write!(f, "{}", square),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum MoveType {
     Normal {
         from: Option<Disambiguation>,
@@ -39,14 +61,95 @@ pub enum MoveType {
     },
 }
 
-#[derive(PartialEq, Eq)]
+impl MoveType {
+    // Check if a generated move, whose origin square is always known,
+    // is a valid reading of this (possibly partially specified) parsed move
+    pub fn matches(&self, generated: &MoveType) -> bool {
+        use MoveType::*;
+        match (self, generated) {
+            (
+                Normal { from, to, piece },
+                Normal {
+                    from: Some(Disambiguation::Square(from_square)),
+                    to: generated_to,
+                    piece: generated_piece,
+                },
+            )
+            | (
+                Capture { from, to, piece },
+                Capture {
+                    from: Some(Disambiguation::Square(from_square)),
+                    to: generated_to,
+                    piece: generated_piece,
+                },
+            ) => {
+                to == generated_to
+                    && piece == generated_piece
+                    && from.is_none_or(|from| from.matches(from_square))
+            }
+            _ => self == generated,
+        }
+    }
+}
+
+// Written as SAN with exactly the disambiguation the move holds,
+// except that pawn pushes never need any and pawns have no piece letter
+impl fmt::Display for MoveType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveType::Normal {
+                to,
+                piece: PieceType::Pawn,
+                ..
+            } => write!(f, "{}", to),
+            MoveType::Normal { from, to, piece } => {
+                write!(f, "{}", piece.to_char())?;
+                if let Some(from) = from {
+                    write!(f, "{}", from)?;
+                }
+                write!(f, "{}", to)
+            }
+            MoveType::Capture { from, to, piece } => {
+                if *piece != PieceType::Pawn {
+                    write!(f, "{}", piece.to_char())?;
+                }
+                if let Some(from) = from {
+                    write!(f, "{}", from)?;
+                }
+                write!(f, "x{}", to)
+            }
+            MoveType::Castle(CastleType::Short) => write!(f, "O-O"),
+            MoveType::Castle(CastleType::Long) => write!(f, "O-O-O"),
+            MoveType::Promotion { to, promote_to } => write!(f, "{}={}", to, promote_to.to_char()),
+            MoveType::PromotionCapture {
+                from,
+                to,
+                promote_to,
+            } => write!(f, "{}x{}={}", from.to_char(), to, promote_to.to_char()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum GameResult {
     WhiteWins,
     BlackWins,
     Draw,
 }
 
-#[derive(PartialEq, Eq)]
+// Written the same way as the result tokens Move::parse accepts
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let result = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        };
+        write!(f, "{}", result)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Move {
     NoCheck(MoveType),
     Check(MoveType),
@@ -55,6 +158,19 @@ pub enum Move {
     EndOfGame(GameResult),
 }
 
+// The inverse of Move::parse
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Move::NoCheck(move_type) => write!(f, "{}", move_type),
+            Move::Check(move_type) => write!(f, "{}+", move_type),
+            Move::Checkmate(move_type) => write!(f, "{}#", move_type),
+            Move::DrawOffer => write!(f, "(=)"),
+            Move::EndOfGame(result) => write!(f, "{}", result),
+        }
+    }
+}
+
 // A symbol for one character of the input
 enum InputToken {
     Piece(PieceType),
@@ -66,7 +182,7 @@ enum InputToken {
     Checkmate,
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum MoveParseError {
     NotAMove,
     InvalidCharacter(usize, char),
@@ -88,6 +204,10 @@ impl Move {
         match input {
             "O-O" => Ok(Move::NoCheck(MoveType::Castle(CastleType::Short))),
             "O-O-O" => Ok(Move::NoCheck(MoveType::Castle(CastleType::Long))),
+            "O-O+" => Ok(Move::Check(MoveType::Castle(CastleType::Short))),
+            "O-O-O+" => Ok(Move::Check(MoveType::Castle(CastleType::Long))),
+            "O-O#" => Ok(Move::Checkmate(MoveType::Castle(CastleType::Short))),
+            "O-O-O#" => Ok(Move::Checkmate(MoveType::Castle(CastleType::Long))),
             "(=)" => Ok(Move::DrawOffer),
             "1-0" => Ok(Move::EndOfGame(GameResult::WhiteWins)),
             "0-1" => Ok(Move::EndOfGame(GameResult::BlackWins)),
@@ -379,6 +499,13 @@ mod tests {
         assert!(parsed == Ok(expected))
     }
 
+    #[test]
+    fn castle_checkmate() {
+        let parsed = Move::parse("O-O-O#");
+        let expected = Checkmate(Castle(CastleType::Long));
+        assert!(parsed == Ok(expected))
+    }
+
     #[test]
     fn check() {
         let parsed = Move::parse("Qh4+");
@@ -446,4 +573,25 @@ mod tests {
         let parsed = Move::parse("Jf3");
         assert!(parsed == Err(MoveParseError::InvalidCharacter(0, 'J')))
     }
+
+    #[test]
+    fn display_round_trip() {
+        let inputs = [
+            "Be5", "c5", "Bxe5", "exd6", "Rdf8", "R1a3", "Qh4e1", "Rdxf8", "R1xa3", "Qh4xe1",
+            "e8=Q", "gxf8=N", "(=)", "O-O", "O-O-O#", "Qh4+", "Qh4#", "1-0", "0-1", "1/2-1/2",
+        ];
+        for input in inputs {
+            assert!(Move::parse(input).unwrap().to_string() == input);
+        }
+    }
+
+    #[test]
+    fn display_pawn_push_without_disambiguation() {
+        let move_ = NoCheck(Normal {
+            from: Some(Disambiguation::Square(square!(E 2))),
+            to: square!(E 4),
+            piece: Pawn,
+        });
+        assert!(move_.to_string() == "e4");
+    }
 }
@@ -0,0 +1,158 @@
+use super::{moves::MoveType, Game};
+
+impl Game {
+    // Count the positions reached by every sequence of legal moves of the given length.
+    // Known counts for standard positions make this the usual check on move generation
+    pub fn perft(&self, depth: u32) -> u64 {
+        self.clone().count_leaves(depth)
+    }
+
+    // Split the perft count by the first move, to narrow down where a wrong count comes from
+    pub fn divide(&self, depth: u32) -> Vec<(MoveType, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        let mut next = self.clone();
+        self.legal_moves()
+            .into_iter()
+            .map(|move_type| {
+                next.make_move(&move_type);
+                let count = next.count_leaves(depth - 1);
+                next.unmake_move();
+                (move_type, count)
+            })
+            .collect()
+    }
+
+    fn count_leaves(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_moves();
+        // The last ply only needs counting, not playing
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves
+            .iter()
+            .map(|move_type| {
+                self.make_move(move_type);
+                let count = self.count_leaves(depth - 1);
+                self.unmake_move();
+                count
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::uci, *};
+
+    // Node counts from https://www.chessprogramming.org/Perft_Results
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6: &str =
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+    fn check_perft(fen: &str, counts: &[u64]) {
+        let game = Game::from_fen(fen).unwrap();
+        for (depth, count) in (1..).zip(counts) {
+            assert!(
+                game.perft(depth) == *count,
+                "Wrong perft({}) for {}",
+                depth,
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn start_position() {
+        check_perft(START, &[20, 400, 8902]);
+    }
+
+    #[test]
+    #[ignore]
+    fn start_position_deep() {
+        check_perft(START, &[20, 400, 8902, 197281, 4865609]);
+    }
+
+    #[test]
+    fn kiwipete() {
+        check_perft(KIWIPETE, &[48, 2039]);
+    }
+
+    #[test]
+    #[ignore]
+    fn kiwipete_deep() {
+        check_perft(KIWIPETE, &[48, 2039, 97862, 4085603]);
+    }
+
+    #[test]
+    fn position_3() {
+        check_perft(POSITION_3, &[14, 191, 2812, 43238]);
+    }
+
+    #[test]
+    #[ignore]
+    fn position_3_deep() {
+        check_perft(POSITION_3, &[14, 191, 2812, 43238, 674624, 11030083]);
+    }
+
+    #[test]
+    fn position_4() {
+        check_perft(POSITION_4, &[6, 264, 9467]);
+    }
+
+    #[test]
+    #[ignore]
+    fn position_4_deep() {
+        check_perft(POSITION_4, &[6, 264, 9467, 422333]);
+    }
+
+    #[test]
+    fn position_5() {
+        check_perft(POSITION_5, &[44, 1486]);
+    }
+
+    #[test]
+    #[ignore]
+    fn position_5_deep() {
+        check_perft(POSITION_5, &[44, 1486, 62379, 2103487]);
+    }
+
+    #[test]
+    fn position_6() {
+        check_perft(POSITION_6, &[46, 2079]);
+    }
+
+    #[test]
+    #[ignore]
+    fn position_6_deep() {
+        check_perft(POSITION_6, &[46, 2079, 89890, 3894594]);
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+        let game = Game::from_fen(KIWIPETE).unwrap();
+        let divide = game.divide(2);
+        assert!(divide.len() == 48);
+        assert!(divide.iter().map(|(_, count)| count).sum::<u64>() == 2039);
+        let castle = divide
+            .iter()
+            .find(|(move_type, _)| uci::format(&game, move_type) == "e1g1")
+            .unwrap();
+        assert!(castle.1 == 43);
+    }
+
+    #[test]
+    fn depth_zero() {
+        let game = Game::from_fen(START).unwrap();
+        assert!(game.perft(0) == 1);
+        assert!(game.divide(0).is_empty());
+    }
+}
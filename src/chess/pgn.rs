@@ -0,0 +1,900 @@
+use std::{
+    cell::Cell,
+    fmt,
+    io::{self, BufRead},
+    iter::Peekable,
+    rc::Rc,
+};
+
+use super::{
+    fen::FenError,
+    game::MoveError,
+    generate_starting_position,
+    moves::{GameResult, MoveParseError},
+    san::{self, Annotations},
+    Color, Game, Move,
+};
+
+// A game read from a PGN record
+pub struct PgnGame {
+    // Tag pairs in the order they appear, e.g. ("White", "Carlsen, Magnus")
+    pub tags: Vec<(String, String)>,
+    // The game replayed from the movetext, with every move in its history
+    pub game: Game,
+    // The result token ending the movetext, or None for an unfinished game (*)
+    pub result: Option<GameResult>,
+}
+
+impl PgnGame {
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        get_tag(&self.tags, name)
+    }
+}
+
+pub fn get_tag<'a>(tags: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|(tag, _)| tag == name)
+        .map(|(_, value)| value.as_str())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PgnErrorKind {
+    // Text that does not fit the PGN format, e.g. an unclosed comment
+    Syntax,
+    InvalidMove(MoveParseError),
+    IllegalMove(MoveError),
+    InvalidFen(FenError),
+    // Reading the input failed, after which no more games are read
+    Io(io::ErrorKind),
+}
+
+// Where reading a PGN failed: the game and ply are both counted from 1,
+// with ply 0 meaning the failure happened before any moves
+#[derive(Debug, PartialEq, Eq)]
+pub struct PgnError {
+    pub game: usize,
+    pub ply: usize,
+    pub token: String,
+    pub kind: PgnErrorKind,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Game {}, ply {}, token \"{}\": ",
+            self.game, self.ply, self.token
+        )?;
+        match &self.kind {
+            PgnErrorKind::Syntax => write!(f, "Invalid PGN syntax"),
+            PgnErrorKind::InvalidMove(e) => write!(f, "{}", e),
+            PgnErrorKind::IllegalMove(e) => write!(f, "{}", e),
+            PgnErrorKind::InvalidFen(e) => write!(f, "{}", e),
+            PgnErrorKind::Io(e) => write!(f, "Failed to read input: {}", e),
+        }
+    }
+}
+
+// Read every game in a PGN database, stopping at the first error
+pub fn read_pgn(input: &str) -> Result<Vec<PgnGame>, PgnError> {
+    PgnReader::new(input.as_bytes()).collect()
+}
+
+// Decides from its tags whether a game should be read
+type TagFilter = Box<dyn Fn(&[(String, String)]) -> bool>;
+
+// Streams the games of a PGN database one at a time, so large files
+// never have to be held in memory. Errors only affect the game they are in,
+// and reading carries on with the next game
+pub struct PgnReader<R: BufRead> {
+    tokenizer: Tokenizer<ReadChars<R>>,
+    // Shared with the character reader, which can't return errors itself
+    io_error: Rc<Cell<Option<io::ErrorKind>>>,
+    filter: Option<TagFilter>,
+    // Games read so far, including skipped and failed ones
+    game_number: usize,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> PgnReader<R> {
+        let io_error = Rc::new(Cell::new(None));
+        PgnReader {
+            tokenizer: Tokenizer::new(ReadChars::new(reader, Rc::clone(&io_error))),
+            io_error,
+            filter: None,
+            game_number: 0,
+        }
+    }
+
+    // Only replay games whose tags pass the filter, others are skipped without
+    // playing their moves. Skipped games are still counted in error game numbers
+    pub fn with_filter<F>(mut self, filter: F) -> PgnReader<R>
+    where
+        F: Fn(&[(String, String)]) -> bool + 'static,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    // Skip the rest of a game with a syntax error, up to its result
+    // or the tags of the next game
+    fn recover(&mut self, mut seen_moves: bool) {
+        while let Some(token) = self.tokenizer.next() {
+            match token {
+                Ok(Token::Result(_)) => return,
+                Ok(Token::Tag(name, value)) if seen_moves => {
+                    self.tokenizer.pending = Some(Token::Tag(name, value));
+                    return;
+                }
+                Ok(Token::Move(_)) => seen_moves = true,
+                Ok(Token::Tag(..)) | Err(_) => (),
+            }
+        }
+    }
+
+    fn is_wanted(&self, record: &Record) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| filter(&record.tags))
+    }
+
+    // The failure is in the game being read, which hasn't been counted yet
+    fn io_error(&self, ply: usize) -> Option<PgnError> {
+        self.io_error.take().map(|kind| PgnError {
+            game: self.game_number + 1,
+            ply,
+            token: String::new(),
+            kind: PgnErrorKind::Io(kind),
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(record) = read_record(&mut self.tokenizer) else {
+                return self.io_error(0).map(Err);
+            };
+            // The moves read before a failure are lost, and the error gives the ply it stopped at
+            let (Ok(partial) | Err((partial, _))) = &record;
+            let ply = match partial.moves.len() {
+                0 => 0,
+                moves => moves + 1,
+            };
+            if let Some(error) = self.io_error(ply) {
+                return Some(Err(error));
+            }
+            self.game_number += 1;
+            let record = match record {
+                Ok(record) => record,
+                Err((record, token)) => {
+                    let seen_moves = !record.moves.is_empty();
+                    self.recover(seen_moves);
+                    // The tags are only known to be complete once the movetext has started
+                    if seen_moves && !self.is_wanted(&record) {
+                        continue;
+                    }
+                    return Some(Err(PgnError {
+                        game: self.game_number,
+                        ply: record.moves.len() + 1,
+                        token,
+                        kind: PgnErrorKind::Syntax,
+                    }));
+                }
+            };
+            if self.is_wanted(&record) {
+                return Some(record.replay(self.game_number));
+            }
+        }
+    }
+}
+
+// Reads characters from a buffered reader a line at a time.
+// Invalid UTF-8 is replaced rather than stopping the whole database
+struct ReadChars<R: BufRead> {
+    reader: R,
+    line: Vec<char>,
+    index: usize,
+    io_error: Rc<Cell<Option<io::ErrorKind>>>,
+}
+
+impl<R: BufRead> ReadChars<R> {
+    fn new(reader: R, io_error: Rc<Cell<Option<io::ErrorKind>>>) -> ReadChars<R> {
+        ReadChars {
+            reader,
+            line: Vec::new(),
+            index: 0,
+            io_error,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ReadChars<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        while self.index >= self.line.len() {
+            let mut bytes = Vec::new();
+            match self.reader.read_until(b'\n', &mut bytes) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    self.line = String::from_utf8_lossy(&bytes).chars().collect();
+                    self.index = 0;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => {
+                    self.io_error.set(Some(e.kind()));
+                    return None;
+                }
+            }
+        }
+        self.index += 1;
+        Some(self.line[self.index - 1])
+    }
+}
+
+// The tags every PGN game must have, in the order they must come, with their unknown values
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+// Keep lines under 80 characters, as the PGN export format asks
+const MAX_LINE_LENGTH: usize = 79;
+
+// Write a game as PGN. Any of the Seven Tag Roster can be given in the tags,
+// except the Result which always comes from how the game ended.
+// Other tags are written after the roster in the order given
+pub fn write_pgn(game: &Game, tags: &[(&str, &str)]) -> String {
+    let result = game
+        .status()
+        .get_result()
+        .map_or(String::from("*"), |result| result.to_string());
+
+    let mut output = String::new();
+    for (name, default) in SEVEN_TAG_ROSTER {
+        let value = match name {
+            "Result" => &result,
+            _ => get_tag_pair(tags, name).unwrap_or(default),
+        };
+        output.push_str(&format_tag(name, value));
+    }
+    let starting_fen = game.get_starting_fen();
+    let is_standard_start = starting_fen == Game::new(generate_starting_position()).to_fen();
+    if !is_standard_start {
+        output.push_str(&format_tag("SetUp", "1"));
+        output.push_str(&format_tag("FEN", starting_fen));
+    }
+    for (name, value) in tags {
+        let is_written = SEVEN_TAG_ROSTER.iter().any(|(roster, _)| roster == name)
+            || (!is_standard_start && ["SetUp", "FEN"].contains(name));
+        if !is_written {
+            output.push_str(&format_tag(name, value));
+        }
+    }
+    output.push('\n');
+
+    // Replay the game from the start, as SAN depends on the position before each move
+    let mut replay = Game::from_fen(starting_fen).expect("Games always have a valid starting FEN");
+    let mut movetext = Vec::new();
+    for (i, move_type) in game.get_move_history().iter().enumerate() {
+        match replay.get_turn() {
+            Color::White => movetext.push(format!("{}.", replay.fullmove_number)),
+            Color::Black if i == 0 => movetext.push(format!("{}...", replay.fullmove_number)),
+            Color::Black => (),
+        }
+        movetext.push(san::format(&replay, move_type));
+        replay.make_move(move_type);
+    }
+    movetext.push(result);
+
+    let mut line = String::new();
+    for token in movetext {
+        if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+            output.push_str(&line);
+            output.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    output.push_str(&line);
+    output.push('\n');
+    output
+}
+
+fn get_tag_pair<'a>(tags: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|(tag, _)| *tag == name)
+        .map(|(_, value)| *value)
+}
+
+fn format_tag(name: &str, value: &str) -> String {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("[{} \"{}\"]\n", name, value)
+}
+
+// The raw text of one game, before any of the moves are played
+struct Record {
+    tags: Vec<(String, String)>,
+    moves: Vec<String>,
+    result: Option<GameResult>,
+}
+
+impl Record {
+    fn replay(self, game_number: usize) -> Result<PgnGame, PgnError> {
+        let error = |ply: usize, token: &str, kind: PgnErrorKind| PgnError {
+            game: game_number,
+            ply,
+            token: token.to_string(),
+            kind,
+        };
+        // Games that don't start from the usual position give it in a FEN tag
+        let mut game = match get_tag(&self.tags, "FEN") {
+            Some(fen) => {
+                Game::from_fen(fen).map_err(|e| error(0, fen, PgnErrorKind::InvalidFen(e)))?
+            }
+            None => Game::new(generate_starting_position()),
+        };
+        for (i, token) in self.moves.iter().enumerate() {
+            let move_ = Move::parse(token)
+                .map_err(|e| error(i + 1, token, PgnErrorKind::InvalidMove(e)))?;
+            // Annotations in game records are often wrong, so only the move itself matters
+            let resolved = san::resolve(&game, &move_, Annotations::Lenient)
+                .map_err(|e| error(i + 1, token, PgnErrorKind::IllegalMove(e)))?;
+            game.make_move(&resolved);
+        }
+        Ok(PgnGame {
+            tags: self.tags,
+            game,
+            result: self.result,
+        })
+    }
+}
+
+// Read the tags and movetext of the next game, returning None once the input runs out.
+// Syntax errors give the record read up to that point and the offending text
+fn read_record<I: Iterator<Item = char>>(
+    tokenizer: &mut Tokenizer<I>,
+) -> Option<Result<Record, (Record, String)>> {
+    let mut record = Record {
+        tags: Vec::new(),
+        moves: Vec::new(),
+        result: None,
+    };
+    loop {
+        match tokenizer.next() {
+            None if record.tags.is_empty() && record.moves.is_empty() => return None,
+            // A game that stops without a result token is treated as unfinished
+            None => break,
+            Some(Err(token)) => return Some(Err((record, token))),
+            Some(Ok(Token::Tag(name, value))) => {
+                // Tags after the movetext belong to the next game
+                if !record.moves.is_empty() {
+                    tokenizer.pending = Some(Token::Tag(name, value));
+                    break;
+                }
+                record.tags.push((name, value));
+            }
+            Some(Ok(Token::Move(token))) => record.moves.push(token),
+            Some(Ok(Token::Result(result))) => {
+                record.result = result;
+                break;
+            }
+        }
+    }
+    Some(Ok(record))
+}
+
+enum Token {
+    Tag(String, String),
+    Move(String),
+    // None for an unfinished game (*)
+    Result(Option<GameResult>),
+}
+
+// Splits PGN text into tags, moves and results, skipping move numbers,
+// comments, NAGs, annotation glyphs and variations
+struct Tokenizer<I: Iterator<Item = char>> {
+    chars: Peekable<I>,
+    // A token that was read too early and should be returned next
+    pending: Option<Token>,
+}
+
+impl<I: Iterator<Item = char>> Tokenizer<I> {
+    fn new(chars: I) -> Tokenizer<I> {
+        Tokenizer {
+            chars: chars.peekable(),
+            pending: None,
+        }
+    }
+
+    // Skip everything up to and including the given character,
+    // returning false if the input ends before it is found
+    fn skip_until(&mut self, end: char) -> bool {
+        self.chars.by_ref().any(|c| c == end)
+    }
+
+    // Skip a variation, which can contain comments and further variations
+    fn skip_variation(&mut self) -> Result<(), String> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.chars.next() {
+                Some('(') => depth += 1,
+                Some(')') => depth -= 1,
+                Some('{') => {
+                    if !self.skip_until('}') {
+                        return Err(String::from("{"));
+                    }
+                }
+                Some(';') => {
+                    self.skip_until('\n');
+                }
+                Some(_) => (),
+                None => return Err(String::from("(")),
+            }
+        }
+        Ok(())
+    }
+
+    fn read_symbol(&mut self) -> String {
+        let mut symbol = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_alphanumeric() || "_+#=:-/".contains(c)) {
+                break;
+            }
+            symbol.push(c);
+            self.chars.next();
+        }
+        symbol
+    }
+
+    fn read_tag(&mut self) -> Result<Token, String> {
+        self.skip_whitespace();
+        let name = self.read_symbol();
+        self.skip_whitespace();
+        if self.chars.next() != Some('"') {
+            return Err(format!("[{}", name));
+        }
+        let mut value = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => break,
+                Some('\\') => match self.chars.next() {
+                    Some(c) => value.push(c),
+                    None => return Err(format!("[{} \"{}", name, value)),
+                },
+                Some(c) => value.push(c),
+                None => return Err(format!("[{} \"{}", name, value)),
+            }
+        }
+        self.skip_whitespace();
+        if self.chars.next() != Some(']') {
+            return Err(format!("[{} \"{}\"", name, value));
+        }
+        Ok(Token::Tag(name, value))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+}
+
+impl<I: Iterator<Item = char>> Iterator for Tokenizer<I> {
+    type Item = Result<Token, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.take() {
+            return Some(Ok(token));
+        }
+        loop {
+            let c = *self.chars.peek()?;
+            match c {
+                _ if c.is_whitespace() || c == '.' || c == '!' || c == '?' => {
+                    self.chars.next();
+                }
+                '{' => {
+                    self.chars.next();
+                    if !self.skip_until('}') {
+                        return Some(Err(String::from("{")));
+                    }
+                }
+                // Comments and escaped lines run to the end of the line
+                ';' | '%' => {
+                    self.skip_until('\n');
+                }
+                '(' => {
+                    self.chars.next();
+                    if let Err(token) = self.skip_variation() {
+                        return Some(Err(token));
+                    }
+                }
+                // Numeric annotation glyphs, e.g. $1
+                '$' => {
+                    self.chars.next();
+                    self.read_symbol();
+                }
+                '[' => {
+                    self.chars.next();
+                    return Some(self.read_tag());
+                }
+                '*' => {
+                    self.chars.next();
+                    return Some(Ok(Token::Result(None)));
+                }
+                _ if c.is_ascii_alphanumeric() => {
+                    let symbol = self.read_symbol();
+                    let token = match symbol.as_str() {
+                        "1-0" => Token::Result(Some(GameResult::WhiteWins)),
+                        "0-1" => Token::Result(Some(GameResult::BlackWins)),
+                        "1/2-1/2" => Token::Result(Some(GameResult::Draw)),
+                        // Move numbers are followed by dots, which are skipped above
+                        _ if symbol.chars().all(|c| c.is_ascii_digit()) => continue,
+                        _ => Token::Move(symbol),
+                    };
+                    return Some(Ok(token));
+                }
+                _ => {
+                    self.chars.next();
+                    return Some(Err(c.to_string()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{moves::MoveType, square, Color, File, PieceType, Rank, Square},
+        *,
+    };
+
+    const IMMORTAL_GAME: &str = r#"[Event "London"]
+[Site "London ENG"]
+[Date "1851.06.21"]
+[Round "?"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+
+1.e4 e5 2.f4 exf4 3.Bc4 Qh4+ 4.Kf1 b5 5.Bxb5 Nf6 6.Nf3 Qh6 7.d3 Nh5 8.Nh4 Qg5
+9.Nf5 c6 10.g4 Nf6 11.Rg1 cxb5 12.h4 Qg6 13.h5 Qg5 14.Qf3 Ng8 15.Bxf4 Qf6
+16.Nc3 Bc5 17.Nd5 Qxb2 18.Bd6 Bxg1 19.e5 Qxa1+ 20.Ke2 Na6 21.Nxg7+ Kd8
+22.Qf6+ Nxf6 23.Be7# 1-0
+"#;
+
+    #[test]
+    fn immortal_game() {
+        let games = read_pgn(IMMORTAL_GAME).unwrap();
+        assert!(games.len() == 1);
+        let pgn_game = &games[0];
+        assert!(pgn_game.get_tag("White") == Some("Anderssen, Adolf"));
+        assert!(pgn_game.get_tag("ECO").is_none());
+        assert!(pgn_game.tags.len() == 7);
+        assert!(pgn_game.result == Some(GameResult::WhiteWins));
+        assert!(pgn_game.game.get_move_history().len() == 45);
+        assert!(pgn_game.game.status() == crate::chess::GameStatus::Checkmate(Color::White));
+    }
+
+    #[test]
+    fn comments_nags_and_variations() {
+        let pgn = r#"[Event "Annotated"]
+
+1. e4 {Best by test} e5 $1 2. Nf3!? (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6 ; Main line
+3. Bb5 a6 $2 {The Morphy Defence} *
+"#;
+        let games = read_pgn(pgn).unwrap();
+        assert!(games[0].result.is_none());
+        let history = games[0].game.get_move_history();
+        assert!(history.len() == 6);
+        assert!(matches!(
+            history[4],
+            MoveType::Normal {
+                to,
+                piece: PieceType::Bishop,
+                ..
+            } if to == square!(B 5)
+        ));
+    }
+
+    #[test]
+    fn multiple_games() {
+        let pgn = r#"[Event "First"]
+[Result "1/2-1/2"]
+
+1. e4 e5 1/2-1/2
+
+[Event "Second"]
+[Result "0-1"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+"#;
+        let games = read_pgn(pgn).unwrap();
+        assert!(games.len() == 2);
+        assert!(games[0].result == Some(GameResult::Draw));
+        assert!(games[1].get_tag("Event") == Some("Second"));
+        assert!(games[1].result == Some(GameResult::BlackWins));
+    }
+
+    #[test]
+    fn fen_tag() {
+        let pgn = r#"[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+1. e4 Kd7 *
+"#;
+        let games = read_pgn(pgn).unwrap();
+        assert!(games[0].game.to_fen() == "8/3k4/8/8/4P3/8/8/4K3 w - - 1 2");
+    }
+
+    #[test]
+    fn escaped_tag() {
+        let pgn = r#"[Annotator "A \"quoted\" name"] 1. e4 *"#;
+        let games = read_pgn(pgn).unwrap();
+        assert!(games[0].get_tag("Annotator") == Some("A \"quoted\" name"));
+    }
+
+    #[test]
+    fn illegal_move() {
+        let pgn = "[Event \"One\"] 1. e4 e5 * [Event \"Two\"] 1. e4 e5 2. Ke3 *";
+        let expected = PgnError {
+            game: 2,
+            ply: 3,
+            token: String::from("Ke3"),
+            kind: PgnErrorKind::IllegalMove(MoveError::IllegalMove),
+        };
+        assert!(read_pgn(pgn).err() == Some(expected));
+    }
+
+    #[test]
+    fn invalid_move() {
+        let expected = PgnError {
+            game: 1,
+            ply: 2,
+            token: String::from("Xe5"),
+            kind: PgnErrorKind::InvalidMove(MoveParseError::InvalidCharacter(0, 'X')),
+        };
+        assert!(read_pgn("1. e4 Xe5 *").err() == Some(expected));
+    }
+
+    #[test]
+    fn unclosed_comment() {
+        let expected = PgnError {
+            game: 1,
+            ply: 2,
+            token: String::from("{"),
+            kind: PgnErrorKind::Syntax,
+        };
+        assert!(read_pgn("1. e4 {no end").err() == Some(expected));
+    }
+
+    #[test]
+    fn invalid_fen() {
+        let pgn = r#"[FEN "8/8/8 w - - 0 1"] *"#;
+        let error = read_pgn(pgn).err().unwrap();
+        assert!(error.ply == 0 && error.token == "8/8/8 w - - 0 1");
+        assert!(matches!(error.kind, PgnErrorKind::InvalidFen(_)));
+    }
+
+    #[test]
+    fn write_immortal_game() {
+        let games = read_pgn(IMMORTAL_GAME).unwrap();
+        let pgn_game = &games[0];
+        let tags: Vec<(&str, &str)> = pgn_game
+            .tags
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        assert!(write_pgn(&pgn_game.game, &tags) == IMMORTAL_GAME_EXPORT);
+    }
+
+    const IMMORTAL_GAME_EXPORT: &str = r#"[Event "London"]
+[Site "London ENG"]
+[Date "1851.06.21"]
+[Round "?"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+
+1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 b5 5. Bxb5 Nf6 6. Nf3 Qh6 7. d3 Nh5 8.
+Nh4 Qg5 9. Nf5 c6 10. g4 Nf6 11. Rg1 cxb5 12. h4 Qg6 13. h5 Qg5 14. Qf3 Ng8 15.
+Bxf4 Qf6 16. Nc3 Bc5 17. Nd5 Qxb2 18. Bd6 Bxg1 19. e5 Qxa1+ 20. Ke2 Na6 21.
+Nxg7+ Kd8 22. Qf6+ Nxf6 23. Be7# 1-0
+"#;
+
+    #[test]
+    fn write_defaults() {
+        let mut game = Game::new(generate_starting_position());
+        game.do_move(Move::parse("e4").unwrap()).unwrap();
+        let pgn = write_pgn(
+            &game,
+            &[("White", "Human"), ("Result", "1-0"), ("ECO", "B00")],
+        );
+        let expected = r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "Human"]
+[Black "?"]
+[Result "*"]
+[ECO "B00"]
+
+1. e4 *
+"#;
+        assert!(pgn == expected);
+    }
+
+    #[test]
+    fn write_from_fen() {
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1";
+        let mut game = Game::from_fen(fen).unwrap();
+        for input in ["Kd7", "e4", "Ke6"] {
+            game.do_move(Move::parse(input).unwrap()).unwrap();
+        }
+        game.do_move(Move::parse("0-1").unwrap()).unwrap();
+        let pgn = write_pgn(&game, &[("Annotator", "A \"quoted\" name")]);
+        assert!(pgn.contains(
+            "[Result \"0-1\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n"
+        ));
+        assert!(pgn.contains("[Annotator \"A \\\"quoted\\\" name\"]\n"));
+        assert!(pgn.ends_with("\n1... Kd7 2. e4 Ke6 0-1\n"));
+
+        let games = read_pgn(&pgn).unwrap();
+        assert!(games[0].game.to_fen() == game.to_fen());
+        assert!(games[0].get_tag("Annotator") == Some("A \"quoted\" name"));
+    }
+
+    const DATABASE: &str = r#"[Event "Najdorf"]
+[White "Player, A"]
+[ECO "B90"]
+
+1. e4 c5 2. Nf3 d6 3. d4 cxd4 4. Nxd4 Nf6 5. Nc3 a6 *
+
+[Event "Broken"]
+[White "Player, B"]
+[ECO "C20"]
+
+1. e4 e5 2. Ke3 1-0
+
+[Event "Bad syntax"]
+[White "Player, A"]
+
+1. e4 & e5 2. Nf3 0-1
+[Event "Italian"]
+[White "Player, C"]
+[ECO "C50"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bc4 1/2-1/2
+"#;
+
+    #[test]
+    fn stream_continues_after_errors() {
+        let results: Vec<_> = PgnReader::new(DATABASE.as_bytes()).collect();
+        assert!(results.len() == 4);
+        assert!(results[0].as_ref().unwrap().game.get_move_history().len() == 10);
+        assert!(matches!(
+            &results[1],
+            Err(PgnError { game: 2, ply: 3, token, kind: PgnErrorKind::IllegalMove(_) }) if token == "Ke3"
+        ));
+        assert!(matches!(
+            &results[2],
+            Err(PgnError {
+                game: 3,
+                ply: 2,
+                kind: PgnErrorKind::Syntax,
+                ..
+            })
+        ));
+        let game = results[3].as_ref().unwrap();
+        assert!(game.get_tag("Event") == Some("Italian"));
+        assert!(game.result == Some(GameResult::Draw));
+    }
+
+    #[test]
+    fn stream_filter() {
+        let sicilians: Vec<_> = PgnReader::new(DATABASE.as_bytes())
+            .with_filter(|tags| {
+                get_tag(tags, "ECO").is_some_and(|eco| ("B90"..="B99").contains(&eco))
+            })
+            .collect();
+        assert!(sicilians.len() == 1);
+        assert!(sicilians[0].as_ref().unwrap().get_tag("Event") == Some("Najdorf"));
+
+        // Syntax errors in the movetext are filtered by the tags before them
+        let player: Vec<_> = PgnReader::new(DATABASE.as_bytes())
+            .with_filter(|tags| get_tag(tags, "White") == Some("Player, A"))
+            .collect();
+        assert!(player.len() == 2);
+        assert!(player[0].is_ok());
+        assert!(matches!(&player[1], Err(PgnError { game: 3, .. })));
+    }
+
+    #[test]
+    fn stream_invalid_utf8() {
+        let mut input = b"[Event \"".to_vec();
+        input.extend_from_slice(&[0xff, 0xfe]);
+        input.extend_from_slice(b"\"]\n\n1. d4 d5 *\n");
+        let games: Vec<_> = PgnReader::new(input.as_slice()).collect();
+        assert!(games.len() == 1);
+        assert!(games[0].as_ref().unwrap().get_tag("Event") == Some("\u{fffd}\u{fffd}"));
+    }
+
+    // Serves the input a chunk per fill_buf, then fails once the chunks run out
+    struct FailingReader {
+        chunks: Vec<&'static [u8]>,
+        offset: usize,
+    }
+
+    impl io::Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let chunk = self.fill_buf()?;
+            let count = chunk.len().min(buf.len());
+            buf[..count].copy_from_slice(&chunk[..count]);
+            self.consume(count);
+            Ok(count)
+        }
+    }
+
+    impl BufRead for FailingReader {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            match self.chunks.first() {
+                Some(chunk) => Ok(&chunk[self.offset..]),
+                None => Err(io::Error::other("disk failure")),
+            }
+        }
+
+        fn consume(&mut self, amount: usize) {
+            self.offset += amount;
+            if self.offset == self.chunks[0].len() {
+                self.chunks.remove(0);
+                self.offset = 0;
+            }
+        }
+    }
+
+    #[test]
+    fn stream_io_error() {
+        let reader = FailingReader {
+            chunks: vec![
+                b"[Event \"A\"]\n1. e4 e5 1-0\n\n",
+                b"[Event \"B\"]\n1. d4 d5 2. c4\n",
+            ],
+            offset: 0,
+        };
+        let games: Vec<_> = PgnReader::new(reader).collect();
+        assert!(games.len() == 2);
+        assert!(games[0].as_ref().unwrap().get_tag("Event") == Some("A"));
+        let expected = PgnError {
+            game: 2,
+            ply: 4,
+            token: String::new(),
+            kind: PgnErrorKind::Io(io::ErrorKind::Other),
+        };
+        assert!(games[1].as_ref().err() == Some(&expected));
+
+        // Failing between games blames the game that was about to be read
+        let reader = FailingReader {
+            chunks: vec![b"[Event \"A\"]\n1. e4 e5 1-0\n"],
+            offset: 0,
+        };
+        let games: Vec<_> = PgnReader::new(reader).collect();
+        assert!(matches!(
+            &games[1],
+            Err(PgnError {
+                game: 2,
+                ply: 0,
+                ..
+            })
+        ));
+    }
+}
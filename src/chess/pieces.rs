@@ -1,6 +1,10 @@
-use crate::chess::{move_, Board, Color, Square};
+use crate::chess::{
+    attacks,
+    board::{get_bit, get_squares},
+    Board, Color, Square,
+};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum PieceType {
     Pawn,
     Knight,
@@ -11,6 +15,14 @@ pub enum PieceType {
 }
 
 impl PieceType {
+    // The pieces a pawn may become when it reaches the last rank
+    pub const PROMOTIONS: [PieceType; 4] = [
+        PieceType::Queen,
+        PieceType::Rook,
+        PieceType::Bishop,
+        PieceType::Knight,
+    ];
+
     pub fn from_char(c: char) -> Option<PieceType> {
         match c {
             'N' => Some(PieceType::Knight),
@@ -21,9 +33,20 @@ impl PieceType {
             _ => None,
         }
     }
+
+    pub fn to_char(self) -> char {
+        match self {
+            PieceType::Pawn => 'P',
+            PieceType::Knight => 'N',
+            PieceType::Bishop => 'B',
+            PieceType::Rook => 'R',
+            PieceType::Queen => 'Q',
+            PieceType::King => 'K',
+        }
+    }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Piece {
     pub piece_type: PieceType,
     pub color: Color,
@@ -40,18 +63,30 @@ pub struct Piece {
 // }
 
 impl Piece {
-    // pub fn get_moves(&self, board: &Board, from_square: &Square) -> Vec<Move> {
-    //     match self.piece_type {
-    //         PieceType::Pawn => self.get_pawn_moves(board, from_square),
-    //         PieceType::Knight => self.get_knight_moves(board, from_square),
-    //         PieceType::Bishop => self.get_bishop_moves(board, from_square),
-    //         PieceType::Rook => self.get_rook_moves(board, from_square),
-    //         PieceType::Queen => self.get_queen_moves(board, from_square),
-    //         PieceType::King => self.get_king_moves(board, from_square),
-    //     }
-    // }
-    //
-    fn get_pawn_moves(&self, board: &Board, from_square: &Square) -> Vec<MoveType> {
+    // The en passant square is where a pawn that has just double pushed can be taken,
+    // which cannot be worked out from the board alone
+    pub fn get_moves(
+        &self,
+        board: &Board,
+        from_square: &Square,
+        en_passant: Option<Square>,
+    ) -> Vec<MoveType> {
+        match self.piece_type {
+            PieceType::Pawn => self.get_pawn_moves(board, from_square, en_passant),
+            PieceType::Knight => self.get_knight_moves(board, from_square),
+            PieceType::Bishop => self.get_bishop_moves(board, from_square),
+            PieceType::Rook => self.get_rook_moves(board, from_square),
+            PieceType::Queen => self.get_queen_moves(board, from_square),
+            PieceType::King => self.get_king_moves(board, from_square),
+        }
+    }
+
+    fn get_pawn_moves(
+        &self,
+        board: &Board,
+        from_square: &Square,
+        en_passant: Option<Square>,
+    ) -> Vec<MoveType> {
         let mut moves = Vec::new();
         // Get the direction the pawn can move in
         let forward = match self.color {
@@ -62,134 +97,110 @@ impl Piece {
         // Check if we can push the pawn forwards
         let result = board.get_offset(from_square, 0, forward);
         if let Some((new_square, None)) = result {
-            moves.push(MoveType::Normal {
-                from: Some(Disambiguation::Square(*from_square)),
-                to: new_square,
-                piece: PieceType::Pawn,
-            });
-        }
+            if self.is_promotion_square(board, &new_square) {
+                moves.extend(PieceType::PROMOTIONS.map(|promote_to| MoveType::Promotion {
+                    to: new_square,
+                    promote_to,
+                }));
+            } else {
+                moves.push(self.normal(from_square, new_square));
+            }
 
-        // Check if we can take a piece diagonally
-        for file_offset in (-1..=1).step_by(2) {
-            let result = board.get_offset(from_square, file_offset, forward);
-            if let Some((new_square, Some(taken_piece))) = result {
-                if taken_piece.color != self.color {
-                    moves.push(MoveType::Normal {
-                        from: Some(Disambiguation::Square(*from_square)),
-                        to: new_square,
-                        piece: PieceType::Pawn,
-                    });
+            // Pawns that haven't moved yet can push two squares if both are empty
+            let start_rank_index = match self.color {
+                Color::White => 1,
+                Color::Black => board.get_height() - 2,
+            };
+            if from_square.rank_index() == start_rank_index {
+                let result = board.get_offset(from_square, 0, 2 * forward);
+                if let Some((new_square, None)) = result {
+                    moves.push(self.normal(from_square, new_square));
                 }
             }
         }
 
-        // moves = moves.iter().map(|m| m).collect();
+        // Pawns take diagonally, including a pawn that has just passed the square en passant
+        let mut targets = board.get_color_bitboard(self.color.opposite());
+        if let Some(en_passant) = en_passant {
+            targets |= get_bit(&en_passant);
+        }
+        for new_square in get_squares(attacks::get_pawn_attacks(self.color, from_square) & targets)
+        {
+            if self.is_promotion_square(board, &new_square) {
+                moves.extend(
+                    PieceType::PROMOTIONS.map(|promote_to| MoveType::PromotionCapture {
+                        from: from_square.file(),
+                        to: new_square,
+                        promote_to,
+                    }),
+                );
+            } else {
+                moves.push(self.capture(from_square, new_square));
+            }
+        }
 
         moves
     }
-    // fn get_knight_moves(&self, board: &Board, from_square: &Square) -> Vec<Move> {
-    //     let mut moves = Vec::new();
-    //     // The knight moves by checking every square in a 5x5 box centered on itself,
-    //     // only moving to squares that are 2 squares away in one direction and 1 square away in the other
-    //     for file_offset in -2i8..=2 {
-    //         for rank_offset in -2i8..=2 {
-    //             if file_offset == 0 || rank_offset == 0 || file_offset.abs() == rank_offset.abs() {
-    //                 continue;
-    //             }
-    //             let result = board.get_offset(from_square, file_offset, rank_offset);
-    //             if let Some((new_square, Some(taken_piece))) = result {
-    //                 // If we hit an opponent's piece, we can take it
-    //                 if taken_piece.color != self.color {
-    //                     moves.push(move_!(*from_square, new_square));
-    //                 }
-    //             } else if let Some((new_square, None)) = result {
-    //                 // Move into empty space
-    //                 moves.push(move_!(*from_square, new_square));
-    //             }
-    //         }
-    //     }
-    //     moves
-    // }
-    //
-    // // Get all moves for a piece that can move in straight lines,
-    // // without the ability to move over pieces (e.g. bishop, rook, queen)
-    // fn get_sliding_moves(
-    //     &self,
-    //     board: &Board,
-    //     from_square: &Square,
-    //     directions: [(i8, i8); 4],
-    // ) -> Vec<Move> {
-    //     let mut moves = Vec::new();
-    //     for (file_direction, rank_direction) in directions.iter() {
-    //         // Offsets represent the vector from the starting square
-    //         let (mut file_offset, mut rank_offset) = (*file_direction, *rank_direction);
-    //         // Sliding is true until we hit another piece or the edge of the board
-    //         let mut sliding: bool = true;
-    //         while sliding {
-    //             let result = board.get_offset(from_square, file_offset, rank_offset);
-    //
-    //             if let Some((new_square, Some(taken_piece))) = result {
-    //                 // If we hit another piece stop sliding,
-    //                 // if that piece is the opposite color, we can take it
-    //                 if taken_piece.color != self.color {
-    //                     moves.push(move_!(*from_square, new_square));
-    //                 }
-    //                 sliding = false;
-    //             } else if let Some((new_square, None)) = result {
-    //                 // If we find an empty square, keep sliding
-    //                 moves.push(move_!(*from_square, new_square));
-    //                 file_offset += *file_direction;
-    //                 rank_offset += *rank_direction;
-    //             } else if result.is_none() {
-    //                 // If we hit the edge of the board, stop sliding
-    //                 sliding = false;
-    //             }
-    //         }
-    //     }
-    //     moves
-    // }
-    //
-    // fn get_bishop_moves(&self, board: &Board, from_square: &Square) -> Vec<Move> {
-    //     const DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
-    //     self.get_sliding_moves(board, from_square, DIRECTIONS)
-    // }
-    //
-    // fn get_rook_moves(&self, board: &Board, from_square: &Square) -> Vec<Move> {
-    //     const DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
-    //     self.get_sliding_moves(board, from_square, DIRECTIONS)
-    // }
-    //
-    // fn get_queen_moves(&self, board: &Board, from_square: &Square) -> Vec<Move> {
-    //     [
-    //         self.get_bishop_moves(board, from_square),
-    //         self.get_rook_moves(board, from_square),
-    //     ]
-    //     .concat()
-    // }
-    //
-    // fn get_king_moves(&self, board: &Board, from_square: &Square) -> Vec<Move> {
-    //     let mut moves = Vec::new();
-    //     // Check every square in a 3x3 box around the king
-    //     for file_offset in -1i8..=1 {
-    //         for rank_offset in -1i8..=1 {
-    //             if file_offset == 0 && rank_offset == 0 {
-    //                 continue;
-    //             }
-    //
-    //             let result = board.get_offset(from_square, file_offset, rank_offset);
-    //             if let Some((new_square, Some(taken_piece))) = result {
-    //                 // If we hit an opponent's piece, we can take it
-    //                 if taken_piece.color != self.color {
-    //                     moves.push(move_!(*from_square, new_square));
-    //                 }
-    //             } else if let Some((new_square, None)) = result {
-    //                 // Move into empty space
-    //                 moves.push(move_!(*from_square, new_square));
-    //             }
-    //         }
-    //     }
-    //     moves
-    // }
+
+    // Check if a pawn of this color moving onto the square would have to promote
+    pub fn is_promotion_square(&self, board: &Board, square: &Square) -> bool {
+        let last_rank_index = match self.color {
+            Color::White => board.get_height() - 1,
+            Color::Black => 0,
+        };
+        square.rank_index() == last_rank_index
+    }
+
+    fn get_knight_moves(&self, board: &Board, from_square: &Square) -> Vec<MoveType> {
+        self.get_attack_moves(board, from_square, attacks::get_knight_attacks(from_square))
+    }
+
+    fn get_bishop_moves(&self, board: &Board, from_square: &Square) -> Vec<MoveType> {
+        let attacks = attacks::get_bishop_attacks(from_square, board.get_occupied_bitboard());
+        self.get_attack_moves(board, from_square, attacks)
+    }
+
+    fn get_rook_moves(&self, board: &Board, from_square: &Square) -> Vec<MoveType> {
+        let attacks = attacks::get_rook_attacks(from_square, board.get_occupied_bitboard());
+        self.get_attack_moves(board, from_square, attacks)
+    }
+
+    fn get_queen_moves(&self, board: &Board, from_square: &Square) -> Vec<MoveType> {
+        let attacks = attacks::get_queen_attacks(from_square, board.get_occupied_bitboard());
+        self.get_attack_moves(board, from_square, attacks)
+    }
+
+    fn get_king_moves(&self, board: &Board, from_square: &Square) -> Vec<MoveType> {
+        self.get_attack_moves(board, from_square, attacks::get_king_attacks(from_square))
+    }
+
+    // Get the moves onto each attacked square (e.g. knight, bishop, king),
+    // which is allowed when the square is empty or holds an opponent's piece
+    fn get_attack_moves(&self, board: &Board, from_square: &Square, attacks: u64) -> Vec<MoveType> {
+        let opponent = board.get_color_bitboard(self.color.opposite());
+        get_squares(attacks & !board.get_color_bitboard(self.color))
+            .map(|to| match get_bit(&to) & opponent {
+                0 => self.normal(from_square, to),
+                _ => self.capture(from_square, to),
+            })
+            .collect()
+    }
+
+    fn normal(&self, from_square: &Square, to: Square) -> MoveType {
+        MoveType::Normal {
+            from: Some(Disambiguation::Square(*from_square)),
+            to,
+            piece: self.piece_type,
+        }
+    }
+
+    fn capture(&self, from_square: &Square, to: Square) -> MoveType {
+        MoveType::Capture {
+            from: Some(Disambiguation::Square(*from_square)),
+            to,
+            piece: self.piece_type,
+        }
+    }
 }
 
 #[macro_export]
@@ -203,7 +214,116 @@ macro_rules! piece {
 }
 pub(crate) use piece;
 
-use super::{
-    moves::{Disambiguation, MoveType},
-    Move,
-};
+use super::moves::{Disambiguation, MoveType};
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{square, File, Rank},
+        *,
+    };
+    use std::collections::HashMap;
+
+    fn count_moves(piece: Piece, square: Square, others: &[(Square, Piece)]) -> usize {
+        let mut position = HashMap::from_iter(others.iter().copied());
+        position.insert(square, piece);
+        let board = Board::new(position);
+        piece.get_moves(&board, &square, None).len()
+    }
+
+    #[test]
+    fn knight_in_centre() {
+        assert!(count_moves(piece!(White, Knight), square!(D 4), &[]) == 8);
+    }
+
+    #[test]
+    fn knight_in_corner() {
+        assert!(count_moves(piece!(White, Knight), square!(A 1), &[]) == 2);
+    }
+
+    #[test]
+    fn bishop_in_centre() {
+        assert!(count_moves(piece!(White, Bishop), square!(D 4), &[]) == 13);
+    }
+
+    #[test]
+    fn rook_in_centre() {
+        assert!(count_moves(piece!(White, Rook), square!(D 4), &[]) == 14);
+    }
+
+    #[test]
+    fn queen_in_centre() {
+        assert!(count_moves(piece!(White, Queen), square!(D 4), &[]) == 27);
+    }
+
+    #[test]
+    fn king_on_edge() {
+        assert!(count_moves(piece!(White, King), square!(E 1), &[]) == 5);
+    }
+
+    #[test]
+    fn pawn_double_push() {
+        assert!(count_moves(piece!(White, Pawn), square!(E 2), &[]) == 2);
+        assert!(count_moves(piece!(Black, Pawn), square!(E 7), &[]) == 2);
+        assert!(count_moves(piece!(White, Pawn), square!(E 3), &[]) == 1);
+    }
+
+    #[test]
+    fn pawn_double_push_blocked() {
+        let blocked_far = [(square!(E 4), piece!(Black, Knight))];
+        assert!(count_moves(piece!(White, Pawn), square!(E 2), &blocked_far) == 1);
+        let blocked_near = [(square!(E 3), piece!(Black, Knight))];
+        assert!(count_moves(piece!(White, Pawn), square!(E 2), &blocked_near) == 0);
+    }
+
+    #[test]
+    fn promotion() {
+        let others = [(square!(D 8), piece!(Black, Rook))];
+        let board = Board::new(HashMap::from_iter(others.iter().copied()));
+        let pawn = piece!(White, Pawn);
+        let moves = pawn.get_moves(&board, &square!(E 7), None);
+        assert!(moves.len() == 8);
+        for promote_to in PieceType::PROMOTIONS {
+            assert!(moves.contains(&MoveType::Promotion {
+                to: square!(E 8),
+                promote_to,
+            }));
+            assert!(moves.contains(&MoveType::PromotionCapture {
+                from: File::E,
+                to: square!(D 8),
+                promote_to,
+            }));
+        }
+    }
+
+    #[test]
+    fn en_passant() {
+        let others = [(square!(D 5), piece!(Black, Pawn))];
+        let board = Board::new(HashMap::from_iter(others.iter().copied()));
+        let pawn = piece!(White, Pawn);
+        let moves = pawn.get_moves(&board, &square!(E 5), Some(square!(D 6)));
+        assert!(moves.len() == 2);
+        assert!(moves.contains(&MoveType::Capture {
+            from: Some(Disambiguation::Square(square!(E 5))),
+            to: square!(D 6),
+            piece: PieceType::Pawn,
+        }));
+    }
+
+    #[test]
+    fn rook_blocked() {
+        let others = [
+            (square!(A 2), piece!(White, Pawn)),
+            (square!(C 1), piece!(Black, Knight)),
+        ];
+        let board = Board::new(HashMap::from_iter(others.iter().copied()));
+        let rook = piece!(White, Rook);
+        let moves = rook.get_moves(&board, &square!(A 1), None);
+        assert!(moves.len() == 2);
+        assert!(moves.contains(&MoveType::Capture {
+            from: Some(Disambiguation::Square(square!(A 1))),
+            to: square!(C 1),
+            piece: PieceType::Rook,
+        }));
+    }
+}
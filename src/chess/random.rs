@@ -0,0 +1,17 @@
+// A small xorshift generator, so anything picked at random from a fixed seed,
+// like Zobrist keys or test positions, is the same in every build and on every run
+pub(crate) struct Random(u64);
+
+impl Random {
+    // The seed must not be zero, or every number after it is zero too
+    pub(crate) const fn new(seed: u64) -> Random {
+        Random(seed)
+    }
+
+    pub(crate) const fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
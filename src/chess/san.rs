@@ -0,0 +1,500 @@
+use super::{
+    game::MoveError,
+    moves::{Disambiguation, Move, MoveType},
+    Game, PieceType, Square,
+};
+
+// How the check (+) and checkmate (#) suffixes of a parsed move are treated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Annotations {
+    // The suffix must match what the move actually does to the opponent's king
+    Strict,
+    // The suffix is ignored, as imported game records often get it wrong
+    Lenient,
+}
+
+// Find the single legal move that a parsed SAN move refers to in the current position.
+// SAN often leaves out the origin square, so it has to be worked out from which pieces
+// can actually reach the target, narrowed down by any file/rank disambiguation
+pub fn resolve(game: &Game, move_: &Move, annotations: Annotations) -> Result<MoveType, MoveError> {
+    let resolved = resolve_move_type(game, move_)?;
+    if annotations == Annotations::Strict {
+        check_annotation(game, move_, &resolved)?;
+    }
+    Ok(resolved)
+}
+
+fn resolve_move_type(game: &Game, move_: &Move) -> Result<MoveType, MoveError> {
+    let move_type = match move_ {
+        Move::NoCheck(move_type) | Move::Check(move_type) | Move::Checkmate(move_type) => move_type,
+        Move::DrawOffer | Move::EndOfGame(_) => return Err(MoveError::NotAMove),
+    };
+
+    match *move_type {
+        MoveType::Promotion { promote_to, .. } | MoveType::PromotionCapture { promote_to, .. }
+            if !PieceType::PROMOTIONS.contains(&promote_to) =>
+        {
+            return Err(MoveError::InvalidPromotion(promote_to));
+        }
+        _ if is_missing_promotion(game, move_type) => {
+            return Err(MoveError::MissingPromotion);
+        }
+        _ => (),
+    }
+
+    let candidates: Vec<MoveType> = game
+        .get_all_possible_moves()
+        .into_iter()
+        .filter(|generated| move_type.matches(generated))
+        .collect();
+    // Pieces that are pinned do not count towards ambiguity
+    let legal: Vec<MoveType> = candidates
+        .iter()
+        .filter(|candidate| game.is_legal(candidate))
+        .copied()
+        .collect();
+    match legal[..] {
+        [resolved] => Ok(resolved),
+        [] if !candidates.is_empty() => Err(MoveError::LeavesKingInCheck),
+        [] => Err(MoveError::IllegalMove),
+        _ => Err(MoveError::AmbiguousMove),
+    }
+}
+
+// Check if a pawn move without a promotion piece would be a legal promotion if it had one
+fn is_missing_promotion(game: &Game, move_type: &MoveType) -> bool {
+    let promote_to = PieceType::Queen;
+    let promotion = match *move_type {
+        MoveType::Normal {
+            to,
+            piece: PieceType::Pawn,
+            ..
+        } => MoveType::Promotion { to, promote_to },
+        MoveType::Capture {
+            from: Some(Disambiguation::File(from)),
+            to,
+            piece: PieceType::Pawn,
+        } => MoveType::PromotionCapture {
+            from,
+            to,
+            promote_to,
+        },
+        _ => return false,
+    };
+    game.get_all_possible_moves().contains(&promotion)
+}
+
+// Check that a move is marked as check or checkmate exactly when it gives one
+fn check_annotation(game: &Game, move_: &Move, resolved: &MoveType) -> Result<(), MoveError> {
+    let mut next = game.clone();
+    next.make_move(resolved);
+    let is_check = next.is_in_check(next.get_turn());
+    let is_checkmate = is_check && next.legal_moves().is_empty();
+    let is_correct = match move_ {
+        Move::NoCheck(_) => !is_check,
+        Move::Check(_) => is_check && !is_checkmate,
+        Move::Checkmate(_) => is_checkmate,
+        Move::DrawOffer | Move::EndOfGame(_) => true,
+    };
+    if is_correct {
+        Ok(())
+    } else {
+        Err(MoveError::IncorrectAnnotation)
+    }
+}
+
+// Write a legal move in the shortest correct SAN for the current position,
+// including any check (+) or checkmate (#) suffix
+pub fn format(game: &Game, move_type: &MoveType) -> String {
+    to_move(game, move_type).to_string()
+}
+
+// Turn a legal move into the Move that SAN would write for it in the current position,
+// with only as much disambiguation as is needed and the right check annotation
+pub fn to_move(game: &Game, move_type: &MoveType) -> Move {
+    let minimal = match *move_type {
+        MoveType::Normal {
+            from: Some(Disambiguation::Square(from)),
+            to,
+            piece,
+        } => MoveType::Normal {
+            from: disambiguate(game, &from, &to, piece),
+            to,
+            piece,
+        },
+        // Pawn captures always name the file they come from
+        MoveType::Capture {
+            from: Some(Disambiguation::Square(from)),
+            to,
+            piece: PieceType::Pawn,
+        } => MoveType::Capture {
+            from: Some(Disambiguation::File(from.file())),
+            to,
+            piece: PieceType::Pawn,
+        },
+        MoveType::Capture {
+            from: Some(Disambiguation::Square(from)),
+            to,
+            piece,
+        } => MoveType::Capture {
+            from: disambiguate(game, &from, &to, piece),
+            to,
+            piece,
+        },
+        MoveType::Castle(_) | MoveType::Promotion { .. } | MoveType::PromotionCapture { .. } => {
+            *move_type
+        }
+        _ => panic!("Tried to format a move that has not been resolved"),
+    };
+
+    let mut next = game.clone();
+    next.make_move(move_type);
+    if !next.is_in_check(next.get_turn()) {
+        Move::NoCheck(minimal)
+    } else if next.legal_moves().is_empty() {
+        Move::Checkmate(minimal)
+    } else {
+        Move::Check(minimal)
+    }
+}
+
+// Only give as much of the origin square as is needed to tell
+// this piece apart from others of the same type that can reach the target
+fn disambiguate(
+    game: &Game,
+    from: &Square,
+    to: &Square,
+    piece: PieceType,
+) -> Option<Disambiguation> {
+    // Pawn pushes can only come from one square
+    if piece == PieceType::Pawn {
+        return None;
+    }
+    let others: Vec<Square> = game
+        .legal_moves()
+        .into_iter()
+        .filter_map(|other| match other {
+            MoveType::Normal {
+                from: Some(Disambiguation::Square(other_from)),
+                to: other_to,
+                piece: other_piece,
+            }
+            | MoveType::Capture {
+                from: Some(Disambiguation::Square(other_from)),
+                to: other_to,
+                piece: other_piece,
+            } if other_to == *to && other_piece == piece && other_from != *from => Some(other_from),
+            _ => None,
+        })
+        .collect();
+    if others.is_empty() {
+        None
+    } else if others.iter().all(|other| other.file() != from.file()) {
+        Some(Disambiguation::File(from.file()))
+    } else if others.iter().all(|other| other.rank() != from.rank()) {
+        Some(Disambiguation::Rank(from.rank()))
+    } else {
+        Some(Disambiguation::Square(*from))
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::{
+        super::{
+            castling::CastlingRights, generate_starting_position, moves::Disambiguation, piece,
+            random::Random, square, Board, Color, File, Piece, Rank, Square,
+        },
+        *,
+    };
+    use std::collections::HashMap;
+
+    fn resolve_input(game: &Game, input: &str) -> Result<MoveType, MoveError> {
+        resolve(game, &Move::parse(input).unwrap(), Annotations::Lenient)
+    }
+
+    fn knights_game() -> Game {
+        Game::new(HashMap::from([
+            (square!(E 1), piece!(White, King)),
+            (square!(B 8), piece!(White, Knight)),
+            (square!(F 6), piece!(White, Knight)),
+            (square!(A 1), piece!(White, Rook)),
+            (square!(A 5), piece!(White, Rook)),
+            (square!(H 8), piece!(Black, King)),
+        ]))
+    }
+
+    #[test]
+    fn ambiguous() {
+        let game = knights_game();
+        assert!(resolve_input(&game, "Nd7") == Err(MoveError::AmbiguousMove));
+        assert!(resolve_input(&game, "Ra3") == Err(MoveError::AmbiguousMove));
+    }
+
+    #[test]
+    fn Nbd7() {
+        let game = knights_game();
+        let expected = MoveType::Normal {
+            from: Some(Disambiguation::Square(square!(B 8))),
+            to: square!(D 7),
+            piece: PieceType::Knight,
+        };
+        assert!(resolve_input(&game, "Nbd7") == Ok(expected));
+    }
+
+    #[test]
+    fn R1a3() {
+        let game = knights_game();
+        let expected = MoveType::Normal {
+            from: Some(Disambiguation::Square(square!(A 1))),
+            to: square!(A 3),
+            piece: PieceType::Rook,
+        };
+        assert!(resolve_input(&game, "R1a3") == Ok(expected));
+    }
+
+    #[test]
+    fn no_such_piece() {
+        let game = knights_game();
+        assert!(resolve_input(&game, "Nc5") == Err(MoveError::IllegalMove));
+        assert!(resolve_input(&game, "Bd5") == Err(MoveError::IllegalMove));
+        assert!(resolve_input(&game, "Ncd7") == Err(MoveError::IllegalMove));
+    }
+
+    #[test]
+    fn pinned_piece_is_not_ambiguous() {
+        // The knight on f2 is pinned by the bishop, so only the knight on c3 can reach e4
+        let game = Game::new(HashMap::from([
+            (square!(E 1), piece!(White, King)),
+            (square!(C 3), piece!(White, Knight)),
+            (square!(F 2), piece!(White, Knight)),
+            (square!(H 4), piece!(Black, Bishop)),
+            (square!(H 8), piece!(Black, King)),
+        ]));
+        let expected = MoveType::Normal {
+            from: Some(Disambiguation::Square(square!(C 3))),
+            to: square!(E 4),
+            piece: PieceType::Knight,
+        };
+        assert!(resolve_input(&game, "Ne4") == Ok(expected));
+    }
+
+    #[test]
+    fn not_a_move() {
+        let game = knights_game();
+        assert!(resolve(&game, &Move::DrawOffer, Annotations::Lenient) == Err(MoveError::NotAMove));
+    }
+
+    #[test]
+    fn en_passant_reading() {
+        let board = Board::new(HashMap::from([
+            (square!(E 5), piece!(White, Pawn)),
+            (square!(D 5), piece!(Black, Pawn)),
+        ]));
+        let en_passant = Some(square!(D 6));
+        let game = Game::from_position(
+            board,
+            Color::White,
+            CastlingRights::none(),
+            en_passant,
+            0,
+            1,
+        );
+        let expected = MoveType::Capture {
+            from: Some(Disambiguation::Square(square!(E 5))),
+            to: square!(D 6),
+            piece: PieceType::Pawn,
+        };
+        assert!(resolve_input(&game, "exd6") == Ok(expected));
+    }
+
+    fn fools_mate() -> Game {
+        let mut game = Game::new(generate_starting_position());
+        for input in ["f3", "e5", "g4"] {
+            game.do_move(Move::parse(input).unwrap()).unwrap();
+        }
+        game
+    }
+
+    fn resolve_strict(game: &Game, input: &str) -> Result<MoveType, MoveError> {
+        resolve(game, &Move::parse(input).unwrap(), Annotations::Strict)
+    }
+
+    #[test]
+    fn strict_checkmate() {
+        let game = fools_mate();
+        assert!(resolve_strict(&game, "Qh4#").is_ok());
+        assert!(resolve_strict(&game, "Qh4+") == Err(MoveError::IncorrectAnnotation));
+        assert!(resolve_strict(&game, "Qh4") == Err(MoveError::IncorrectAnnotation));
+    }
+
+    #[test]
+    fn strict_not_checkmate() {
+        let game = fools_mate();
+        assert!(resolve_strict(&game, "Qg5#") == Err(MoveError::IncorrectAnnotation));
+        assert!(resolve_strict(&game, "Qg5+") == Err(MoveError::IncorrectAnnotation));
+        assert!(resolve_strict(&game, "Qg5").is_ok());
+    }
+
+    #[test]
+    fn lenient_ignores_annotations() {
+        let game = fools_mate();
+        assert!(resolve_input(&game, "Qg5#").is_ok());
+        assert!(resolve_input(&game, "Qh4").is_ok());
+    }
+
+    fn format_input(game: &Game, input: &str) -> String {
+        format(game, &resolve_input(game, input).unwrap())
+    }
+
+    #[test]
+    fn format_disambiguation() {
+        let game = knights_game();
+        assert!(format_input(&game, "Nbd7") == "Nbd7");
+        assert!(format_input(&game, "Nb8d7") == "Nbd7");
+        assert!(format_input(&game, "Ne4") == "Ne4");
+        assert!(format_input(&game, "R1a3") == "R1a3");
+        assert!(format_input(&game, "Rb1") == "Rb1");
+    }
+
+    #[test]
+    fn format_square_disambiguation() {
+        let game = Game::new(HashMap::from([
+            (square!(A 1), piece!(White, King)),
+            (square!(H 4), piece!(White, Queen)),
+            (square!(E 4), piece!(White, Queen)),
+            (square!(H 1), piece!(White, Queen)),
+            (square!(B 6), piece!(Black, King)),
+        ]));
+        assert!(format_input(&game, "Qh4e1") == "Qh4e1");
+        assert!(format_input(&game, "Qe4e1") == "Qee1");
+        assert!(format_input(&game, "Qh1e1") == "Q1e1");
+    }
+
+    #[test]
+    fn format_pawn_moves() {
+        let board = Board::new(HashMap::from([
+            (square!(E 5), piece!(White, Pawn)),
+            (square!(D 5), piece!(Black, Pawn)),
+            (square!(G 7), piece!(White, Pawn)),
+            (square!(F 8), piece!(Black, Rook)),
+            (square!(A 1), piece!(White, King)),
+            (square!(H 7), piece!(Black, King)),
+        ]));
+        let en_passant = Some(square!(D 6));
+        let game = Game::from_position(
+            board,
+            Color::White,
+            CastlingRights::none(),
+            en_passant,
+            0,
+            1,
+        );
+        assert!(format_input(&game, "exd6") == "exd6");
+        assert!(format_input(&game, "g8=Q") == "g8=Q+");
+        assert!(format_input(&game, "gxf8=N") == "gxf8=N+");
+    }
+
+    #[test]
+    fn format_checkmate() {
+        let game = fools_mate();
+        assert!(format_input(&game, "Qh4") == "Qh4#");
+        assert!(format_input(&game, "Qg5") == "Qg5");
+    }
+
+    #[test]
+    fn format_castling() {
+        let game = Game::from_fen("3k4/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        assert!(format_input(&game, "O-O") == "O-O");
+        assert!(format_input(&game, "O-O-O") == "O-O-O+");
+    }
+
+    #[test]
+    fn format_long_castle_checkmate() {
+        let game = Game::from_fen("3k4/8/2Q3N1/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert!(format_input(&game, "O-O-O") == "O-O-O#");
+    }
+
+    #[test]
+    fn to_move_keeps_disambiguation_minimal() {
+        let game = knights_game();
+        let move_ = to_move(&game, &resolve_input(&game, "Nb8d7").unwrap());
+        let expected = Move::NoCheck(MoveType::Normal {
+            from: Some(Disambiguation::File(File::B)),
+            to: square!(D 7),
+            piece: PieceType::Knight,
+        });
+        assert!(move_ == expected);
+    }
+
+    // Every way of writing the move with less disambiguation than was given
+    fn shorter_moves(move_: &Move) -> Vec<Move> {
+        let (move_type, rebuild): (MoveType, fn(MoveType) -> Move) = match *move_ {
+            Move::NoCheck(move_type) => (move_type, Move::NoCheck),
+            Move::Check(move_type) => (move_type, Move::Check),
+            Move::Checkmate(move_type) => (move_type, Move::Checkmate),
+            _ => return Vec::new(),
+        };
+        let (from, to, piece, is_capture) = match move_type {
+            MoveType::Normal { from, to, piece } => (from, to, piece, false),
+            MoveType::Capture { from, to, piece } => (from, to, piece, true),
+            _ => return Vec::new(),
+        };
+        let shorter = match from {
+            // Pawn captures must always give their file
+            _ if piece == PieceType::Pawn && is_capture => vec![],
+            Some(Disambiguation::Square(square)) => vec![
+                None,
+                Some(Disambiguation::File(square.file())),
+                Some(Disambiguation::Rank(square.rank())),
+            ],
+            Some(_) => vec![None],
+            None => vec![],
+        };
+        shorter
+            .into_iter()
+            .map(|from| {
+                rebuild(match is_capture {
+                    true => MoveType::Capture { from, to, piece },
+                    false => MoveType::Normal { from, to, piece },
+                })
+            })
+            .collect()
+    }
+
+    // Play random games, checking that every legal move along the way is written
+    // in SAN that reads back as the same move and can't be made any shorter
+    #[test]
+    fn format_round_trip_random_games() {
+        let mut random = Random::new(0x9e37_79b9_7f4a_7c15);
+        let positions = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "1n2k3/P4N2/8/3Q4/8/8/2N1N3/4K2Q w - - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        ];
+        for fen in positions {
+            let mut game = Game::from_fen(fen).unwrap();
+            for _ in 0..12 {
+                let legal_moves = game.legal_moves();
+                if legal_moves.is_empty() {
+                    break;
+                }
+                for move_type in &legal_moves {
+                    let san = format(&game, move_type);
+                    let parsed = Move::parse(&san).unwrap();
+                    assert!(parsed.to_string() == san);
+                    assert!(resolve(&game, &parsed, Annotations::Strict) == Ok(*move_type));
+                    for shorter in shorter_moves(&parsed) {
+                        assert!(
+                            resolve(&game, &shorter, Annotations::Strict)
+                                == Err(MoveError::AmbiguousMove)
+                        );
+                    }
+                }
+                let chosen = legal_moves[random.next() as usize % legal_moves.len()];
+                game.make_move(&chosen);
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt;
 
-use enum_iterator::{all, cardinality, Sequence};
+use enum_iterator::Sequence;
 
 #[derive(Debug, Sequence, Hash, Eq, PartialEq, Copy, Clone)]
 pub enum File {
@@ -35,7 +35,7 @@ impl File {
         }
     }
 
-    pub fn from_index(i: usize) -> Option<File> {
+    pub const fn from_index(i: usize) -> Option<File> {
         match i {
             0 => Some(File::A),
             1 => Some(File::B),
@@ -49,7 +49,7 @@ impl File {
         }
     }
 
-    pub fn to_char(&self) -> char {
+    pub fn to_char(self) -> char {
         match self {
             File::A => 'a',
             File::B => 'b',
@@ -77,11 +77,7 @@ pub enum Rank {
 
 impl fmt::Display for Rank {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{:?}",
-            all::<Rank>().position(|r| r == *self).unwrap() + 1
-        )
+        write!(f, "{:?}", *self as usize + 1)
     }
 }
 
@@ -99,57 +95,89 @@ impl Rank {
             _ => None,
         }
     }
-}
 
-#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
-pub struct Square {
-    rank: Rank,
-    file: File,
-    file_index: usize,
-    rank_index: usize,
+    pub const fn from_index(i: usize) -> Option<Rank> {
+        match i {
+            0 => Some(Rank::One),
+            1 => Some(Rank::Two),
+            2 => Some(Rank::Three),
+            3 => Some(Rank::Four),
+            4 => Some(Rank::Five),
+            5 => Some(Rank::Six),
+            6 => Some(Rank::Seven),
+            7 => Some(Rank::Eight),
+            _ => None,
+        }
+    }
 }
 
+// A square stored as its index from 0 to 63, counting along each rank from a1 to h8.
+// Squares are ordered the same way, so a1 < h1 < a2 < h8
+#[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+pub struct Square(u8);
+
 impl Square {
-    pub fn new(file: File, rank: Rank) -> Square {
-        Square {
-            file,
-            rank,
-            file_index: all::<File>().position(|f| f == file).unwrap(),
-            rank_index: all::<Rank>().position(|r| r == rank).unwrap(),
-        }
+    pub const fn new(file: File, rank: Rank) -> Square {
+        // Both enums are declared in board order, so their discriminants are their indices
+        Square(rank as u8 * 8 + file as u8)
     }
 
-    pub fn from_index(file_index: usize, rank_index: usize) -> Square {
-        if file_index >= cardinality::<File>() || rank_index >= cardinality::<Rank>() {
+    pub const fn from_index(file_index: usize, rank_index: usize) -> Square {
+        if file_index >= 8 || rank_index >= 8 {
             panic!("Tried to create a Square with an out of bounds index")
         }
-        Square {
-            file: all::<File>().collect::<Vec<_>>()[file_index],
-            rank: all::<Rank>().collect::<Vec<_>>()[rank_index],
-            file_index,
-            rank_index,
+        Square((rank_index * 8 + file_index) as u8)
+    }
+
+    // The inverse of index()
+    pub const fn from_square_index(index: usize) -> Square {
+        if index >= 64 {
+            panic!("Tried to create a Square with an out of bounds index")
         }
+        Square(index as u8)
+    }
+
+    // The square's position in the order a1, b1, ..., h1, a2, ..., h8,
+    // which is also its bit in a bitboard
+    pub const fn index(self) -> usize {
+        self.0 as usize
     }
 
-    // Use getters to ensure that the values cannot be changed,
-    // violating the in-bounds guarantee of using Rank/File enums
-    pub fn get_file_index(&self) -> usize {
-        self.file_index
+    pub const fn file_index(self) -> usize {
+        self.0 as usize % 8
     }
 
-    pub fn get_rank_index(&self) -> usize {
-        self.rank_index
+    pub const fn rank_index(self) -> usize {
+        self.0 as usize / 8
     }
 
-    pub fn is_light_square(&self) -> bool {
-        (self.file_index + self.rank_index) % 2 == 0
+    pub const fn file(self) -> File {
+        File::from_index(self.file_index()).unwrap()
+    }
+
+    pub const fn rank(self) -> Rank {
+        Rank::from_index(self.rank_index()).unwrap()
+    }
+
+    // Get the square some offset away, or None if that is off the board
+    pub const fn offset(self, file_offset: i8, rank_offset: i8) -> Option<Square> {
+        let file_index = self.file_index() as i8 + file_offset;
+        let rank_index = self.rank_index() as i8 + rank_offset;
+        if file_index < 0 || file_index >= 8 || rank_index < 0 || rank_index >= 8 {
+            None
+        } else {
+            Some(Square::from_index(file_index as usize, rank_index as usize))
+        }
+    }
+
+    pub const fn is_light_square(self) -> bool {
+        (self.file_index() + self.rank_index()).is_multiple_of(2)
     }
 }
 
-// Use getters for file and rank indices to keep the index-in-bounds safety of using enums
 impl fmt::Display for Square {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}{}", self.file, self.rank)
+        write!(f, "{}{}", self.file().to_char(), self.rank())
     }
 }
 
@@ -173,3 +201,51 @@ macro_rules! square {
     };
 }
 pub(crate) use square;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const E4: Square = square!(E 4);
+
+    #[test]
+    fn one_byte() {
+        assert!(std::mem::size_of::<Square>() == 1);
+        assert!(std::mem::size_of::<Option<Square>>() == 2);
+    }
+
+    #[test]
+    fn accessors() {
+        assert!(E4.file() == File::E && E4.rank() == Rank::Four);
+        assert!(E4.file_index() == 4 && E4.rank_index() == 3);
+        assert!(E4.index() == 28);
+        assert!(Square::from_square_index(28) == E4);
+        assert!(Square::from_index(4, 3) == E4);
+        assert!(E4.to_string() == "e4");
+    }
+
+    #[test]
+    fn offsets() {
+        assert!(E4.offset(1, 2) == Some(square!(F 6)));
+        assert!(E4.offset(-4, -3) == Some(square!(A 1)));
+        assert!(E4.offset(4, 0).is_none());
+        assert!(E4.offset(0, -4).is_none());
+        assert!(square!(H 8).offset(1, 0).is_none());
+    }
+
+    #[test]
+    fn ordered_by_rank_then_file() {
+        assert!(square!(A 1) < square!(H 1));
+        assert!(square!(H 1) < square!(A 2));
+        let map = BTreeMap::from([(square!(A 2), 'x'), (square!(H 1), 'y'), (E4, 'z')]);
+        let keys: Vec<Square> = map.into_keys().collect();
+        assert!(keys == vec![square!(H 1), square!(A 2), E4]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds() {
+        Square::from_index(8, 0);
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt;
+
+use super::{
+    castling::CastleSquares,
+    game::MoveError,
+    moves::{Disambiguation, MoveParseError, MoveType},
+    File, Game, PieceType, Rank, Square,
+};
+
+// A move in the coordinate notation engines and GUIs use, e.g. e2e4 or e7e8q.
+// Castling is written as the king's move, e.g. e1g1
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UciMove {
+    pub from: Square,
+    pub to: Square,
+    pub promote_to: Option<PieceType>,
+}
+
+impl fmt::Display for UciMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(promote_to) = self.promote_to {
+            write!(f, "{}", promote_to.to_char().to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+impl UciMove {
+    pub fn parse(input: &str) -> Result<UciMove, MoveParseError> {
+        let chars: Vec<char> = input.trim_end_matches('\n').chars().collect();
+        if chars.len() != 4 && chars.len() != 5 {
+            return Err(MoveParseError::NotAMove);
+        }
+        let parse_square = |i: usize| {
+            let file =
+                File::from_char(chars[i]).ok_or(MoveParseError::InvalidCharacter(i, chars[i]))?;
+            let rank = Rank::from_char(chars[i + 1])
+                .ok_or(MoveParseError::InvalidCharacter(i + 1, chars[i + 1]))?;
+            Ok(Square::new(file, rank))
+        };
+        let from = parse_square(0)?;
+        let to = parse_square(2)?;
+        // Promotions are always lowercase, so they can't be mistaken for a file
+        let promote_to = match chars.get(4) {
+            Some(&c) if c.is_ascii_lowercase() => Some(
+                PieceType::from_char(c.to_ascii_uppercase())
+                    .ok_or(MoveParseError::InvalidCharacter(4, c))?,
+            ),
+            Some(&c) => return Err(MoveParseError::InvalidCharacter(4, c)),
+            None => None,
+        };
+        Ok(UciMove {
+            from,
+            to,
+            promote_to,
+        })
+    }
+
+    // Write a resolved move as coordinates, which only depend on the side to move
+    pub fn from_move_type(game: &Game, move_type: &MoveType) -> UciMove {
+        match *move_type {
+            MoveType::Normal {
+                from: Some(Disambiguation::Square(from)),
+                to,
+                ..
+            }
+            | MoveType::Capture {
+                from: Some(Disambiguation::Square(from)),
+                to,
+                ..
+            } => UciMove {
+                from,
+                to,
+                promote_to: None,
+            },
+            MoveType::Castle(castle_type) => {
+                let castle_squares = CastleSquares::new(game.get_turn(), castle_type);
+                UciMove {
+                    from: castle_squares.king_from,
+                    to: castle_squares.king_to,
+                    promote_to: None,
+                }
+            }
+            MoveType::Promotion { to, promote_to } => UciMove {
+                from: game.get_promotion_origin(to.file(), &to),
+                to,
+                promote_to: Some(promote_to),
+            },
+            MoveType::PromotionCapture {
+                from,
+                to,
+                promote_to,
+            } => UciMove {
+                from: game.get_promotion_origin(from, &to),
+                to,
+                promote_to: Some(promote_to),
+            },
+            _ => panic!("Tried to format a move that has not been resolved"),
+        }
+    }
+}
+
+// Find the legal move with the given origin, target and promotion
+pub fn resolve(game: &Game, uci_move: &UciMove) -> Result<MoveType, MoveError> {
+    if let Some(promote_to) = uci_move.promote_to {
+        if !PieceType::PROMOTIONS.contains(&promote_to) {
+            return Err(MoveError::InvalidPromotion(promote_to));
+        }
+    }
+    let candidates: Vec<MoveType> = game
+        .get_all_possible_moves()
+        .into_iter()
+        .filter(|generated| {
+            let generated = UciMove::from_move_type(game, generated);
+            generated.from == uci_move.from && generated.to == uci_move.to
+        })
+        .collect();
+    if uci_move.promote_to.is_none()
+        && candidates.iter().any(|candidate| {
+            matches!(
+                candidate,
+                MoveType::Promotion { .. } | MoveType::PromotionCapture { .. }
+            )
+        })
+    {
+        return Err(MoveError::MissingPromotion);
+    }
+    let candidate = candidates
+        .into_iter()
+        .find(|candidate| UciMove::from_move_type(game, candidate) == *uci_move)
+        .ok_or(MoveError::IllegalMove)?;
+    if game.is_legal(&candidate) {
+        Ok(candidate)
+    } else {
+        Err(MoveError::LeavesKingInCheck)
+    }
+}
+
+// Write a legal move in coordinate notation
+pub fn format(game: &Game, move_type: &MoveType) -> String {
+    UciMove::from_move_type(game, move_type).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{generate_starting_position, moves::CastleType, square, Move},
+        *,
+    };
+
+    fn resolve_input(game: &Game, input: &str) -> Result<MoveType, MoveError> {
+        resolve(game, &UciMove::parse(input).unwrap())
+    }
+
+    #[test]
+    fn parse() {
+        let expected = UciMove {
+            from: square!(E 2),
+            to: square!(E 4),
+            promote_to: None,
+        };
+        assert!(UciMove::parse("e2e4") == Ok(expected));
+        let expected = UciMove {
+            from: square!(E 7),
+            to: square!(E 8),
+            promote_to: Some(PieceType::Queen),
+        };
+        assert!(UciMove::parse("e7e8q") == Ok(expected));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(UciMove::parse("e2") == Err(MoveParseError::NotAMove));
+        assert!(UciMove::parse("e2e4e5") == Err(MoveParseError::NotAMove));
+        assert!(UciMove::parse("e2i4") == Err(MoveParseError::InvalidCharacter(2, 'i')));
+        assert!(UciMove::parse("e2e9") == Err(MoveParseError::InvalidCharacter(3, '9')));
+        assert!(UciMove::parse("e7e8Q") == Err(MoveParseError::InvalidCharacter(4, 'Q')));
+        assert!(UciMove::parse("e7e8x") == Err(MoveParseError::InvalidCharacter(4, 'x')));
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for input in ["e2e4", "e7e8q", "a7b8n", "e1g1"] {
+            assert!(UciMove::parse(input).unwrap().to_string() == input);
+        }
+    }
+
+    #[test]
+    fn resolve_normal_moves() {
+        let game = Game::new(generate_starting_position());
+        let expected = MoveType::Normal {
+            from: Some(Disambiguation::Square(square!(G 1))),
+            to: square!(F 3),
+            piece: PieceType::Knight,
+        };
+        assert!(resolve_input(&game, "g1f3") == Ok(expected));
+        assert!(resolve_input(&game, "e2e5") == Err(MoveError::IllegalMove));
+        assert!(resolve_input(&game, "e4e5") == Err(MoveError::IllegalMove));
+    }
+
+    #[test]
+    fn resolve_castling() {
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        let expected = MoveType::Castle(CastleType::Long);
+        assert!(resolve_input(&game, "e8c8") == Ok(expected));
+        assert!(format(&game, &expected) == "e8c8");
+        let expected = MoveType::Castle(CastleType::Short);
+        assert!(resolve_input(&game, "e8g8") == Ok(expected));
+    }
+
+    #[test]
+    fn resolve_promotions() {
+        let game = Game::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let expected = MoveType::Promotion {
+            to: square!(A 8),
+            promote_to: PieceType::Queen,
+        };
+        assert!(resolve_input(&game, "a7a8q") == Ok(expected));
+        let expected = MoveType::PromotionCapture {
+            from: File::A,
+            to: square!(B 8),
+            promote_to: PieceType::Knight,
+        };
+        assert!(resolve_input(&game, "a7b8n") == Ok(expected));
+        assert!(format(&game, &expected) == "a7b8n");
+        assert!(resolve_input(&game, "a7a8") == Err(MoveError::MissingPromotion));
+        assert!(resolve_input(&game, "a7a8k") == Err(MoveError::InvalidPromotion(PieceType::King)));
+        assert!(resolve_input(&game, "e1e2q") == Err(MoveError::IllegalMove));
+    }
+
+    #[test]
+    fn resolve_pinned_piece() {
+        let game = Game::from_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert!(resolve_input(&game, "e2c3") == Err(MoveError::LeavesKingInCheck));
+    }
+
+    #[test]
+    fn play_coordinate_moves() {
+        let mut game = Game::new(generate_starting_position());
+        for input in ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "e1g1"] {
+            game.do_uci_move(&UciMove::parse(input).unwrap()).unwrap();
+        }
+        let mut expected = Game::new(generate_starting_position());
+        for input in ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "O-O"] {
+            expected.do_move(Move::parse(input).unwrap()).unwrap();
+        }
+        assert!(game.to_fen() == expected.to_fen());
+    }
+}
@@ -0,0 +1,187 @@
+use super::{
+    attacks, board::get_squares, castling::CastlingRights, random::Random, Board, Color, Game,
+    Piece, PieceType, Square,
+};
+
+// A random number for each feature of a position. A position's key is the xor
+// of the numbers for every feature it has, so features can be added or removed
+// one at a time by xoring their number in again
+struct Keys {
+    // Indexed by colour, then piece type, then square index
+    pieces: [[[u64; 64]; 6]; 2],
+    black_to_move: u64,
+    // White short, white long, black short, black long
+    castling: [u64; 4],
+    en_passant_files: [u64; 8],
+}
+
+const KEYS: Keys = generate_keys();
+
+// Fill the keys from a fixed seed, so they are the same in every build
+const fn generate_keys() -> Keys {
+    let mut random = Random::new(0x9e37_79b9_7f4a_7c15);
+    let mut keys = Keys {
+        pieces: [[[0; 64]; 6]; 2],
+        black_to_move: 0,
+        castling: [0; 4],
+        en_passant_files: [0; 8],
+    };
+    let mut i = 0;
+    while i < 2 * 6 * 64 {
+        keys.pieces[i / (6 * 64)][i / 64 % 6][i % 64] = random.next();
+        i += 1;
+    }
+    keys.black_to_move = random.next();
+    let mut i = 0;
+    while i < 4 {
+        keys.castling[i] = random.next();
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 8 {
+        keys.en_passant_files[i] = random.next();
+        i += 1;
+    }
+    keys
+}
+
+pub fn get_piece_key(piece: Piece, square: &Square) -> u64 {
+    KEYS.pieces[piece.color as usize][piece.piece_type as usize][square.index()]
+}
+
+pub fn get_turn_key(turn: Color) -> u64 {
+    match turn {
+        Color::White => 0,
+        Color::Black => KEYS.black_to_move,
+    }
+}
+
+pub fn get_castling_key(castling_rights: &CastlingRights) -> u64 {
+    [
+        castling_rights.white_short,
+        castling_rights.white_long,
+        castling_rights.black_short,
+        castling_rights.black_long,
+    ]
+    .iter()
+    .zip(KEYS.castling)
+    .filter(|(has_right, _)| **has_right)
+    .fold(0, |key, (_, castling_key)| key ^ castling_key)
+}
+
+// The en passant file only counts when a pawn of the side to move could take there,
+// so the key doesn't change after every double push
+pub fn get_en_passant_key(board: &Board, turn: Color, en_passant: Option<Square>) -> u64 {
+    let pawns = board.get_piece_bitboard(Piece {
+        piece_type: PieceType::Pawn,
+        color: turn,
+    });
+    match en_passant {
+        // A pawn can take on the square if a pawn of the other colour there would attack it
+        Some(square) if attacks::get_pawn_attacks(turn.opposite(), &square) & pawns != 0 => {
+            KEYS.en_passant_files[square.file_index()]
+        }
+        _ => 0,
+    }
+}
+
+// The key for just the pieces on a board
+pub fn get_board_key(board: &Board) -> u64 {
+    get_squares(board.get_occupied_bitboard())
+        .map(|square| get_piece_key(*board.get(&square).unwrap(), &square))
+        .fold(0, |key, piece_key| key ^ piece_key)
+}
+
+// Work out a game's key from scratch, rather than from the moves that led to it
+pub fn compute(game: &Game) -> u64 {
+    get_board_key(game.get_board())
+        ^ get_turn_key(game.get_turn())
+        ^ get_castling_key(&game.get_castling_rights())
+        ^ get_en_passant_key(game.get_board(), game.get_turn(), game.get_en_passant())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::Move, *};
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for input in moves {
+            game.do_move(Move::parse(input).unwrap()).unwrap();
+        }
+    }
+
+    fn start() -> Game {
+        Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap()
+    }
+
+    #[test]
+    fn keys_are_distinct() {
+        let mut all_keys: Vec<u64> = KEYS.pieces.iter().flatten().flatten().copied().collect();
+        all_keys.push(KEYS.black_to_move);
+        all_keys.extend(KEYS.castling);
+        all_keys.extend(KEYS.en_passant_files);
+        let count = all_keys.len();
+        all_keys.sort();
+        all_keys.dedup();
+        assert!(all_keys.len() == count);
+    }
+
+    #[test]
+    fn transpositions_share_a_key() {
+        let mut first = start();
+        play(&mut first, &["Nf3", "Nf6", "e3", "e6"]);
+        let mut second = start();
+        play(&mut second, &["e3", "e6", "Nf3", "Nf6"]);
+        assert!(first.get_zobrist_key() == second.get_zobrist_key());
+        assert!(first.get_zobrist_key() != start().get_zobrist_key());
+    }
+
+    #[test]
+    fn side_to_move_changes_key() {
+        let white = Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let black = Game::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert!(white.get_zobrist_key() ^ black.get_zobrist_key() == KEYS.black_to_move);
+    }
+
+    #[test]
+    fn castling_rights_change_key() {
+        let all = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let some = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1").unwrap();
+        assert!(
+            all.get_zobrist_key() ^ some.get_zobrist_key() == KEYS.castling[1] ^ KEYS.castling[2]
+        );
+    }
+
+    #[test]
+    fn en_passant_only_counts_when_capturable() {
+        let mut game = start();
+        play(&mut game, &["e4"]);
+        let no_capture =
+            Game::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert!(game.get_zobrist_key() == no_capture.get_zobrist_key());
+
+        let mut game = start();
+        play(&mut game, &["e4", "Nf6", "e5", "d5"]);
+        let capture = "rnbqkb1r/ppp1pppp/5n2/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3";
+        let without = "rnbqkb1r/ppp1pppp/5n2/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3";
+        assert!(game.get_zobrist_key() == Game::from_fen(capture).unwrap().get_zobrist_key());
+        assert!(
+            game.get_zobrist_key() ^ Game::from_fen(without).unwrap().get_zobrist_key()
+                == KEYS.en_passant_files[3]
+        );
+    }
+
+    #[test]
+    fn unmake_restores_key() {
+        let mut game =
+            Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let key = game.get_zobrist_key();
+        for move_type in game.legal_moves() {
+            game.make_move(&move_type);
+            assert!(game.get_zobrist_key() == compute(&game));
+            game.unmake_move();
+            assert!(game.get_zobrist_key() == key);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::chess;
 use std::{
     collections::HashMap,
+    fs,
     io::{self, Write},
 };
 
@@ -9,11 +10,26 @@ enum Player {
     Computer,
 }
 
+impl Player {
+    fn get_name(&self) -> &'static str {
+        match self {
+            Player::Human => "Human",
+            Player::Computer => "Computer",
+        }
+    }
+}
+
 pub struct Cli {
     game: chess::Game,
     players: HashMap<chess::Color, Player>,
 }
 
+impl Default for Cli {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Cli {
     pub fn new() -> Cli {
         Cli {
@@ -25,49 +41,185 @@ impl Cli {
         }
     }
 
-    pub fn run(&self) {
+    pub fn run(&mut self) {
         loop {
-            match self.players.get(&self.game.turn) {
+            let status = self.game.status();
+            if status != chess::GameStatus::Ongoing {
+                println!("{}\n", get_board_string(&self.game));
+                println!("{}", status);
+                self.offer_save();
+                break;
+            }
+            match self.players.get(&self.game.get_turn()) {
                 Some(Player::Human) => {
                     let move_input = loop {
-                        let raw_input = self.get_player_input();
+                        let Some(raw_input) = self.get_player_input() else {
+                            // Stop when the input runs out, e.g. when moves are piped in
+                            return;
+                        };
+                        if raw_input.trim() == "fen" {
+                            println!("{}", self.game.to_fen());
+                            continue;
+                        }
+                        if raw_input.trim() == "undo" || raw_input.trim() == "redo" {
+                            self.step_history(raw_input.trim() == "undo");
+                            continue;
+                        }
+                        if raw_input.trim() == "moves" {
+                            println!("{}", self.get_legal_moves());
+                            continue;
+                        }
+                        if let Some(depth) = raw_input.trim().strip_prefix("perft ") {
+                            self.print_perft(depth.trim());
+                            continue;
+                        }
+                        if let Some(path) = raw_input.trim().strip_prefix("save ") {
+                            self.save(path.trim());
+                            continue;
+                        }
+                        // Coordinate moves like e2e4 are never valid SAN, so try SAN first
                         let parsed_input = chess::Move::parse(&raw_input);
                         match parsed_input {
-                            Ok(move_input) => break move_input,
-                            Err(e) => println!("Input Error! {}", e),
+                            Ok(move_input) => break Ok(move_input),
+                            Err(e) => match chess::uci::UciMove::parse(&raw_input) {
+                                Ok(uci_move) => break Err(uci_move),
+                                Err(_) => println!("Input Error! {}", e),
+                            },
                         }
                     };
-                    self.game.do_move(move_input);
+                    let result = match move_input {
+                        Ok(move_input) => self.game.do_move(move_input),
+                        Err(uci_move) => self.game.do_uci_move(&uci_move),
+                    };
+                    if let Err(e) = result {
+                        println!("Move Error! {}", e);
+                    }
                 }
-                Some(Player::Computer) => (),
-                None => panic!("No player found for color {}", self.game.turn),
+                Some(Player::Computer) => self.play_computer_move(),
+                None => panic!("No player found for color {}", self.game.get_turn()),
             }
         }
     }
 
-    fn get_player_input(&self) -> String {
-        println!("{}\n", get_board_string(&self.game));
-        print!("{} to move > ", self.game.turn);
+    // There is no engine yet, so the computer plays its first legal move
+    fn play_computer_move(&mut self) {
+        let move_type = self.game.legal_moves()[0];
+        let uci_move = chess::uci::UciMove::from_move_type(&self.game, &move_type);
+        println!(
+            "{} plays {}",
+            self.game.get_turn(),
+            chess::san::format(&self.game, &move_type)
+        );
+        self.game
+            .do_uci_move(&uci_move)
+            .expect("A legal move can always be played");
+    }
+
+    // Undo or redo moves until it is a human's turn again,
+    // so the computer's reply is taken back along with the player's move
+    fn step_history(&mut self, is_undo: bool) {
+        loop {
+            let result = match is_undo {
+                true => self.game.undo(),
+                false => self.game.redo(),
+            };
+            if let Err(e) = result {
+                println!("Move Error! {}", e);
+                break;
+            }
+            if let Some(Player::Human) = self.players.get(&self.game.get_turn()) {
+                break;
+            }
+        }
+    }
+
+    fn get_legal_moves(&self) -> String {
+        let mut moves: Vec<String> = self
+            .game
+            .legal_moves()
+            .iter()
+            .map(|move_type| chess::san::format(&self.game, move_type))
+            .collect();
+        moves.sort();
+        moves.join(" ")
+    }
+
+    // Print the perft count after each legal move, then the total
+    fn print_perft(&self, depth: &str) {
+        let depth = match depth.parse::<u32>() {
+            Ok(depth) if depth > 0 => depth,
+            _ => {
+                println!("Input Error! Perft depth must be a positive number");
+                return;
+            }
+        };
+        let mut lines: Vec<(String, u64)> = self
+            .game
+            .divide(depth)
+            .iter()
+            .map(|(move_type, count)| (chess::uci::format(&self.game, move_type), *count))
+            .collect();
+        lines.sort();
+        for (uci_move, count) in &lines {
+            println!("{}: {}", uci_move, count);
+        }
+        let total: u64 = lines.iter().map(|(_, count)| count).sum();
+        println!("\nNodes searched: {}", total);
+    }
+
+    fn save(&self, path: &str) {
+        let white = self.players[&chess::Color::White].get_name();
+        let black = self.players[&chess::Color::Black].get_name();
+        let pgn = chess::pgn::write_pgn(&self.game, &[("White", white), ("Black", black)]);
+        match fs::write(path, pgn) {
+            Ok(()) => println!("Saved game to {}", path),
+            Err(e) => println!("Save Error! {}", e),
+        }
+    }
+
+    fn offer_save(&self) {
+        print!("Save game as (leave empty to skip) > ");
         io::stdout().flush().expect("Failed to flush stdout");
 
         let mut input = String::new();
         io::stdin()
             .read_line(&mut input)
             .expect("Failed to read line");
+        if !input.trim().is_empty() {
+            self.save(input.trim());
+        }
+    }
+
+    fn get_player_input(&self) -> Option<String> {
+        println!("{}\n", get_board_string(&self.game));
+        if let Some(reason) = self.game.claimable_draw() {
+            println!("{} can claim a draw by {}", self.game.get_turn(), reason);
+        }
+        if let Some(color) = self.game.draw_offer {
+            println!("{} offers a draw, accept with 1/2-1/2", color);
+        }
+        print!("{} to move > ", self.game.get_turn());
+        io::stdout().flush().expect("Failed to flush stdout");
+
+        let mut input = String::new();
+        let read = io::stdin()
+            .read_line(&mut input)
+            .expect("Failed to read line");
 
-        input
+        (read > 0).then_some(input)
     }
 }
 
 pub fn get_board_string(game: &chess::Game) -> String {
-    let mut chars = vec![vec!['\u{2022}'; game.board.get_width()]; game.board.get_height()];
-    for (square, piece) in game.board.get_all_pieces() {
-        let x = square.get_file_index();
-        let y = square.get_rank_index();
+    let mut chars =
+        vec![vec!['\u{2022}'; game.get_board().get_width()]; game.get_board().get_height()];
+    for (square, piece) in game.get_board().get_all_pieces() {
+        let x = square.file_index();
+        let y = square.rank_index();
         chars[y][x] = get_piece_unicode(Some(piece));
     }
     let mut ranks = String::from("  ");
-    for x in 0..game.board.get_width() {
+    for x in 0..game.get_board().get_width() {
         ranks.push_str(&format!(
             " {}",
             chess::File::from_index(x).unwrap().to_char()
@@ -77,7 +229,7 @@ pub fn get_board_string(game: &chess::Game) -> String {
         .iter()
         .map(|row| row.iter().map(|c| format!(" {c}")).collect::<String>())
         .enumerate()
-        .map(|(i, row)| format!("{} {}\n", game.board.get_height() - i, row))
+        .map(|(i, row)| format!("{} {}\n", game.get_board().get_height() - i, row))
         .collect::<String>();
     board_output.push_str(&ranks);
     board_output
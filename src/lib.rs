@@ -0,0 +1,2 @@
+pub mod chess;
+pub mod cli;
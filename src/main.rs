@@ -1,7 +1,4 @@
-use std::rc::Rc;
-
-mod chess;
-mod cli;
+use chess::cli;
 
 fn main() {
     let mut cli = cli::Cli::new();